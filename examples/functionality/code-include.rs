@@ -0,0 +1,17 @@
+use std::collections::HashMap;
+
+/// Counts how often each word occurs in the text.
+fn word_count(text: &str) -> HashMap<&str, usize> {
+    let mut counts = HashMap::new();
+    for word in text.split_whitespace() {
+        // ANCHOR: count
+        *counts.entry(word).or_insert(0) += 1;
+        // ANCHOR_END: count
+    }
+    counts
+}
+
+fn main() {
+    let counts = word_count("the quick brown fox jumps over the lazy dog");
+    println!("{:?}", counts);
+}
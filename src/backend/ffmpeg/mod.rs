@@ -27,6 +27,7 @@ struct CurrentFrame(u32);
 impl<'a> Backend<'a> for SlidesFfmpegEspeak {
     type Text = <Beamer as Backend<'a>>::Text;
     type Latex = <Beamer as Backend<'a>>::Latex;
+    type Comment = <Beamer as Backend<'a>>::Comment;
     type FootnoteReference = <Beamer as Backend<'a>>::FootnoteReference;
     type BiberReferences = <Beamer as Backend<'a>>::BiberReferences;
     type Url = <Beamer as Backend<'a>>::Url;
@@ -74,6 +75,8 @@ impl<'a> Backend<'a> for SlidesFfmpegEspeak {
     type Equation = <Beamer as Backend<'a>>::Equation;
     type NumberedEquation = <Beamer as Backend<'a>>::NumberedEquation;
     type Graphviz = <Beamer as Backend<'a>>::Graphviz;
+    type Gnuplot = <Beamer as Backend<'a>>::Gnuplot;
+    type Tikz = <Beamer as Backend<'a>>::Tikz;
 
     fn new() -> Self {
         SlidesFfmpegEspeak {
@@ -205,7 +208,7 @@ impl<'a> StatefulCodeGenUnit<'a, SlidesFfmpegEspeak, CodeBlock<'a>> for CodeBloc
         cfg: &'a Config, code_block: Spanned<CodeBlock<'a>>,
         gen: &mut Generator<'a, SlidesFfmpegEspeak, impl Write>,
     ) -> Result<Self> {
-        let Spanned { value: CodeBlock { label: _, caption: _, language, basicstyle: _ }, .. } = &code_block;
+        let Spanned { value: CodeBlock { label: _, caption: _, language, basicstyle: _, style: _ }, .. } = &code_block;
 
         if let Some(Spanned { value: language, .. }) = language {
             if language.as_ref() == "espeak" {
@@ -36,8 +36,10 @@ impl<'a> CodeGenUnit<'a, ()> for BlockQuoteGen {
         let mut source = None;
         if let Some(pos) = quote.trim_end().rfind('\n') {
             let src = &quote[pos + 1..];
-            if src.starts_with("--") {
-                let src = src.trim_start_matches('-');
+            // `--` is already replaced by the typographer unless smart punctuation is disabled
+            let dash = ["--", "\\textemdash{}", "\\textendash{}"].iter().find(|&dash| src.starts_with(dash));
+            if let Some(dash) = dash {
+                let src = src[dash.len()..].trim_start_matches('-');
                 source = Some(src.trim());
                 quote = &quote[..pos + 1];
             }
@@ -1,26 +1,80 @@
+use std::fs::File;
 use std::io::Write;
+use std::path::PathBuf;
 use diagnostic::Spanned;
 
+use super::graphviz::create_temp_file;
+use crate::backend::latex::highlight;
 use crate::backend::{Backend, CodeGenUnit};
-use crate::config::Config;
-use crate::error::Result;
-use crate::generator::event::{CodeBlock, Event};
+use crate::config::{Config, Highlighter};
+use crate::error::{DiagnosticCode, Result};
+use crate::generator::event::{CodeBlock, CodeStyle, Event};
 use crate::generator::Generator;
-use crate::util::OutJoiner;
+use crate::util::{OutJoiner, ToUnix};
 
 #[derive(Debug)]
-pub struct CodeBlockGen;
+pub struct CodeBlockGen {
+    /// Within tables the code is written to a file and included with `\lstinputlisting` or
+    /// `\VerbatimInput`, as neither `lstlisting` nor `Verbatim` can be used in the body of
+    /// `tabularx`.
+    listing: Option<(File, PathBuf)>,
+    options: String,
+    /// Code collected for the built-in highlighter, which is highlighted when it's complete
+    highlighted: Option<Highlighted>,
+}
+
+#[derive(Debug)]
+struct Highlighted {
+    code: Vec<u8>,
+    language: String,
+    label: Option<String>,
+    caption: Option<String>,
+    style: CodeStyle,
+}
 
 impl<'a> CodeGenUnit<'a, CodeBlock<'a>> for CodeBlockGen {
     fn new(
-        _cfg: &'a Config, code_block: Spanned<CodeBlock<'a>>,
+        cfg: &'a Config, code_block: Spanned<CodeBlock<'a>>,
         gen: &mut Generator<'a, impl Backend<'a>, impl Write>,
     ) -> Result<Self> {
-        let Spanned { value: CodeBlock { label, caption, language, basicstyle }, .. } = code_block;
+        let Spanned { value: CodeBlock { label, caption, language, basicstyle, style }, span } = code_block;
+
+        let language = language.and_then(|Spanned { value: language, span }| {
+            let language = cfg.code.language(&language).to_string();
+            let builtin = cfg.code.highlighter() == Highlighter::Builtin;
+            if (builtin && highlight::supports(&language)) || listings_supports(&language) {
+                return Some(Spanned::new(language, span));
+            }
+            gen.diagnostics()
+                .warning(DiagnosticCode::UnknownCodeLanguage)
+                .with_error_label(span, format!("unknown language `{}`", language))
+                .with_note("falling back to the default style of code blocks")
+                .with_note("add an alias to a known language in the config: `[code.languages]`")
+                .emit();
+            None
+        });
+        let builtin = cfg.code.highlighter() == Highlighter::Builtin
+            && language.as_ref().map_or(false, |language| highlight::supports(&language.value));
+        let in_table = gen.iter_stack().any(|e| e.is_table());
+        let listing = if in_table { Some(create_temp_file(cfg, gen, span, "listing")?) } else { None };
+
+        if builtin {
+            let options = verbatim_options(basicstyle.as_ref().map(|s| s.value.as_ref()), &style)?;
+            if !in_table {
+                writeln!(gen.get_out(), "\\begin{{Verbatim}}[{}]", options)?;
+            }
+            let highlighted = Highlighted {
+                code: Vec::new(),
+                language: language.unwrap().value,
+                label: label.map(|label| label.value.into_owned()),
+                caption: caption.map(|caption| caption.value.into_owned()),
+                style,
+            };
+            return Ok(CodeBlockGen { listing, options, highlighted: Some(highlighted) });
+        }
 
-        let mut out = gen.get_out();
-        write!(out, "\\begin{{lstlisting}}[")?;
-        let mut joiner = OutJoiner::new(&mut out, ", ");
+        let mut options = Vec::new();
+        let mut joiner = OutJoiner::new(&mut options, ", ");
         if let Some(Spanned { value: label, .. }) = label {
             joiner.join(format_args!("label={{{}}}", label))?;
         }
@@ -28,27 +82,135 @@ impl<'a> CodeGenUnit<'a, CodeBlock<'a>> for CodeBlockGen {
             joiner.join(format_args!("caption={{{}}}", caption))?;
         }
         if let Some(Spanned { value: language, .. }) = language {
-            // some fixes for weird latex-specific language naming
-            let language = match language.as_ref() {
-                "asm-x86" | "x86" | "x86-asm" => "[x86_64]{Assembler}",
-                lang => lang
-            };
             joiner.join(format_args!("language={{{}}}", language))?;
         }
         if let Some(Spanned { value: basicstyle, .. }) = basicstyle {
             joiner.join(format_args!("basicstyle={{{basicstyle}}}"))?;
         }
+        match style.linenos {
+            Some(true) => joiner.join(format_args!("numbers=left"))?,
+            Some(false) => joiner.join(format_args!("numbers=none"))?,
+            None => (),
+        }
+        if let Some(firstline) = style.firstline {
+            joiner.join(format_args!("firstnumber={}", firstline))?;
+        }
+        if !style.highlight.is_empty() {
+            // defined in the preamble, compared against the printed line numbers
+            let ranges: Vec<_> = style.highlight.iter()
+                .map(|&(start, end)| format!("{}-{}", start, end))
+                .collect();
+            joiner.join(format_args!("highlightlines={{{}}}", ranges.join(",")))?;
+        }
+        if style.mathescape {
+            joiner.join(format_args!("mathescape=true"))?;
+        }
+        let options = String::from_utf8(options).unwrap();
 
-        writeln!(out, "]")?;
+        if !in_table {
+            writeln!(gen.get_out(), "\\begin{{lstlisting}}[{}]", options)?;
+        }
+        Ok(CodeBlockGen { listing, options, highlighted: None })
+    }
 
-        Ok(CodeBlockGen)
+    fn output_redirect(&mut self) -> Option<&mut dyn Write> {
+        match (&mut self.highlighted, &mut self.listing) {
+            (Some(highlighted), _) => Some(&mut highlighted.code),
+            (None, Some((file, _))) => Some(file),
+            (None, None) => None,
+        }
     }
 
     fn finish(
         self, gen: &mut Generator<'a, impl Backend<'a>, impl Write>,
         _peek: Option<Spanned<&Event<'a>>>,
     ) -> Result<()> {
-        writeln!(gen.get_out(), "\\end{{lstlisting}}")?;
+        let CodeBlockGen { listing, options, highlighted } = self;
+        let highlighted = match highlighted {
+            Some(highlighted) => highlighted,
+            None => {
+                match listing {
+                    None => writeln!(gen.get_out(), "\\end{{lstlisting}}")?,
+                    Some((file, path)) => {
+                        drop(file);
+                        let path = path.to_unix().expect(&format!("non-utf8 path: {:?}", path));
+                        writeln!(gen.get_out(), "\\lstinputlisting[{}]{{{}}}", options, path)?;
+                    },
+                }
+                return Ok(());
+            },
+        };
+
+        let Highlighted { code, language, label, caption, style } = highlighted;
+        let code = String::from_utf8(code).expect("code block isn't valid utf8");
+        let tokens = highlight::tokenize(&language, &code).unwrap();
+        let mut latex = String::new();
+        let firstline = style.firstline.unwrap_or(1);
+        highlight::write_latex(&tokens, firstline, &style.highlight, style.mathescape, &mut latex);
+        match listing {
+            None => {
+                write!(gen.get_out(), "{}", latex)?;
+                writeln!(gen.get_out(), "\\end{{Verbatim}}")?;
+            },
+            Some((mut file, path)) => {
+                write!(file, "{}", latex)?;
+                drop(file);
+                let path = path.to_unix().expect(&format!("non-utf8 path: {:?}", path));
+                writeln!(gen.get_out(), "\\VerbatimInput[{}]{{{}}}", options, path)?;
+            },
+        }
+        // like lstlisting, use the caption and counter of listings
+        if caption.is_some() || label.is_some() {
+            writeln!(gen.get_out(), "\\captionof{{lstlisting}}{{{}}}", caption.as_deref().unwrap_or(""))?;
+        }
+        if let Some(label) = label {
+            writeln!(gen.get_out(), "\\label{{{}}}", label)?;
+        }
         Ok(())
     }
 }
+
+/// Languages of the `listings` package and those defined in the preamble.
+const LISTINGS_LANGUAGES: &[&str] = &[
+    "abap", "acsl", "ada", "algol", "ant", "assembler", "awk", "bash", "basic", "c", "c++", "caml",
+    "cil", "clean", "cobol", "comal 80", "command.com", "comsol", "csh", "delphi", "eiffel",
+    "elan", "erlang", "euphoria", "fortran", "gcl", "gnuplot", "hansl", "haskell", "html", "idl",
+    "inform", "java", "js", "jvmis", "ksh", "lingo", "lisp", "llvm", "logo", "make", "mathematica",
+    "matlab", "mercury", "metapost", "miranda", "mizar", "ml", "modula-2", "mupad", "nastran",
+    "oberon-2", "ocl", "octave", "oz", "pascal", "perl", "php", "pl/i", "plasm", "postscript",
+    "pov", "prolog", "promela", "pstricks", "python", "r", "reduce", "rexx", "rsl", "ruby", "rust",
+    "s", "sas", "scala", "scilab", "sh", "shelxl", "simula", "sparql", "sql", "tcl", "tex",
+    "vbscript", "verilog", "vhdl", "vrml", "xml", "xslt",
+];
+
+/// Returns if `listings` knows the language, which may have a dialect like `[x86_64]{Assembler}`.
+fn listings_supports(language: &str) -> bool {
+    let language = match language.find(']') {
+        Some(idx) if language.starts_with('[') => &language[idx + 1..],
+        _ => language,
+    };
+    let language = language.trim_start_matches('{').trim_end_matches('}').to_lowercase();
+    LISTINGS_LANGUAGES.contains(&language.as_str())
+}
+
+/// Options of the `Verbatim` environment, matching the style of `lstlisting` set in the preamble.
+fn verbatim_options(basicstyle: Option<&str>, style: &CodeStyle) -> Result<String> {
+    let mut options = Vec::new();
+    let mut joiner = OutJoiner::new(&mut options, ", ");
+    joiner.join(format_args!("commandchars=\\\\\\{{\\}}"))?;
+    joiner.join(format_args!("frame=single, framesep=3pt, xleftmargin=8pt, numbersep=5pt"))?;
+    match basicstyle {
+        Some(basicstyle) => joiner.join(format_args!("formatcom={{{}}}", basicstyle))?,
+        None => joiner.join(format_args!("fontsize=\\scriptsize"))?,
+    }
+    if style.linenos != Some(false) {
+        joiner.join(format_args!("numbers=left"))?;
+    }
+    if let Some(firstline) = style.firstline {
+        joiner.join(format_args!("firstnumber={}", firstline))?;
+    }
+    if style.mathescape {
+        joiner.join(format_args!("codes={{\\catcode`\\$=3\\catcode`\\^=7\\catcode`\\_=8}}"))?;
+    }
+    Ok(String::from_utf8(options).unwrap())
+}
@@ -0,0 +1,163 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+use diagnostic::{Span, Spanned};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::graphviz::{create_temp_file, with_program_error};
+use crate::backend::latex::InlineEnvironment;
+use crate::backend::{Backend, CodeGenUnit};
+use crate::config::Config;
+use crate::error::{DiagnosticCode, Error, Result};
+use crate::generator::event::{Event, Gnuplot, GnuplotTerminal};
+use crate::generator::{Generator, JobError};
+use crate::util::ToUnix;
+
+/// Number of lines written before the script, setting terminal and output
+const HEADER_LINES: usize = 2;
+
+/// Renders the plot with `gnuplot` while generating.
+///
+/// Unlike other renders, plots aren't cached, as scripts can read arbitrary data files whose
+/// changes wouldn't be noticed.
+#[derive(Debug)]
+pub struct GnuplotGen<'a> {
+    path: PathBuf,
+    file: File,
+    /// Working directory of gnuplot, such that data files can be given relative to the document
+    document_folder: PathBuf,
+    gnuplot: Gnuplot<'a>,
+    span: Span,
+}
+
+impl<'a> CodeGenUnit<'a, Gnuplot<'a>> for GnuplotGen<'a> {
+    fn new(
+        cfg: &Config, gnuplot: Spanned<Gnuplot<'a>>,
+        gen: &mut Generator<'a, impl Backend<'a>, impl Write>,
+    ) -> Result<Self> {
+        let Spanned { value: gnuplot, span } = gnuplot;
+        let (mut file, path) = create_temp_file(cfg, gen, span, "gnuplot")?;
+        // gnuplot runs in the document folder
+        let path = env::current_dir().map(|dir| dir.join(&path)).unwrap_or(path);
+        let (terminal, extension) = match gnuplot.terminal {
+            GnuplotTerminal::Cairolatex => ("cairolatex pdf", "tex"),
+            GnuplotTerminal::Pdfcairo => ("pdfcairo", "pdf"),
+        };
+        let output = path.with_extension(extension);
+        let output = output.to_unix().expect(&format!("non-utf8 path: {:?}", output));
+        writeln!(file, "set terminal {}", terminal)?;
+        writeln!(file, "set output '{}'", output.replace('\'', "''"))?;
+        Ok(GnuplotGen { path, file, document_folder: cfg.document_folder.clone(), gnuplot, span })
+    }
+
+    fn output_redirect(&mut self) -> Option<&mut dyn Write> {
+        Some(&mut self.file)
+    }
+
+    fn finish(
+        self, gen: &mut Generator<'a, impl Backend<'a>, impl Write>,
+        _peek: Option<Spanned<&Event<'a>>>,
+    ) -> Result<()> {
+        drop(self.file);
+        let Gnuplot { label, caption, scale, width, height, terminal, script_span } = self.gnuplot;
+        let out = match Command::new("gnuplot").arg(&self.path).current_dir(&self.document_folder).output() {
+            Ok(out) => out,
+            Err(e) => {
+                gen.diagnostics()
+                    .error(DiagnosticCode::GnuplotError)
+                    .with_error_label(self.span, "can't render this gnuplot code block")
+                    .with_note(format!("error executing `gnuplot`: {}", e))
+                    .with_note("make sure gnuplot is installed and in your PATH")
+                    .with_note("skipping over it")
+                    .emit();
+                return Err(Error::Diagnostic);
+            },
+        };
+        if !out.status.success() {
+            let stderr = String::from_utf8_lossy(&out.stderr);
+            let script = fs::read_to_string(&self.path).unwrap_or_default();
+            let script = script.splitn(HEADER_LINES + 1, '\n').nth(HEADER_LINES).unwrap_or("");
+            let error = JobError::new(DiagnosticCode::GnuplotError, "error trying to render this gnuplot code block");
+            let parsed = parse_error(&stderr).map(|(line, message)| (Some(line), message));
+            let error = with_program_error(
+                error, "gnuplot", parsed, script, script_span,
+                || format!("{}:\n{}", out.status, stderr.trim_end()),
+            );
+            error.with_note("skipping over it").emit(self.span, gen.diagnostics());
+            return Err(Error::Diagnostic);
+        }
+
+        let out = gen.get_out();
+        let inline_fig = InlineEnvironment::new_figure(label, caption);
+        inline_fig.write_begin(&mut *out)?;
+
+        match terminal {
+            GnuplotTerminal::Cairolatex => {
+                // text is typeset by LaTeX, so the plot can only be resized as a whole
+                let path = self.path.with_extension("tex");
+                let path = path.to_unix().expect(&format!("non-utf8 path: {:?}", path));
+                if let Some(Spanned { value: scale, .. }) = &scale {
+                    write!(out, "\\scalebox{{{}}}{{", scale)?;
+                }
+                if width.is_some() || height.is_some() {
+                    let width = width.as_ref().map_or("!", |w| &*w.value);
+                    let height = height.as_ref().map_or("!", |h| &*h.value);
+                    write!(out, "\\resizebox{{{}}}{{{}}}{{", width, height)?;
+                }
+                write!(out, "\\input{{{}}}", path)?;
+                if width.is_some() || height.is_some() {
+                    write!(out, "}}")?;
+                }
+                if scale.is_some() {
+                    write!(out, "}}")?;
+                }
+                writeln!(out)?;
+            },
+            GnuplotTerminal::Pdfcairo => {
+                write!(out, "\\includegraphics[")?;
+                if let Some(Spanned { value: scale, .. }) = scale {
+                    write!(out, "scale={},", scale)?;
+                }
+                if let Some(Spanned { value: width, .. }) = width {
+                    write!(out, "width={},", width)?;
+                }
+                if let Some(Spanned { value: height, .. }) = height {
+                    write!(out, "height={},", height)?;
+                }
+                let path = self.path.with_extension("pdf");
+                writeln!(out, "]{{{}}}", path.to_unix().expect(&format!("non-utf8 path: {:?}", path)))?;
+            },
+        }
+
+        inline_fig.write_end(out)?;
+        Ok(())
+    }
+}
+
+/// Parses the last error of gnuplot's stderr like `"gnuplot_0", line 5: undefined variable: foo`,
+/// returning the line within the script and the message.
+fn parse_error(stderr: &str) -> Option<(usize, String)> {
+    lazy_static! {
+        static ref ERROR: Regex = Regex::new(r#"(?m)^".*", line (\d+): (.*)$"#).unwrap();
+    }
+    let captures = ERROR.captures_iter(stderr).last()?;
+    let line: usize = captures[1].parse().ok()?;
+    let line = line.checked_sub(HEADER_LINES).filter(|&line| line > 0)?;
+    Some((line, captures[2].trim().to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_error;
+
+    #[test]
+    fn test_parse_error() {
+        let stderr = "\nplot sin(x) with foo\n                 ^\n\"/tmp/gnuplot_0\", line 4: unrecognized plot option\n\n";
+        assert_eq!(parse_error(stderr), Some((2, "unrecognized plot option".to_string())));
+        assert_eq!(parse_error("\"gnuplot_0\", line 1: can't open output"), None);
+        assert_eq!(parse_error("gnuplot: not found"), None);
+    }
+}
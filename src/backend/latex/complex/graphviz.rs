@@ -1,21 +1,28 @@
-use std::fs::{File, OpenOptions};
+use std::fs::{self, File, OpenOptions};
 use std::io::{ErrorKind, Write};
 use std::path::PathBuf;
 use std::process::Command;
 use diagnostic::{Span, Spanned};
+use lazy_static::lazy_static;
+use regex::Regex;
+
 
 use crate::backend::latex::InlineEnvironment;
 use crate::backend::{Backend, CodeGenUnit};
+use crate::cache::{self, CacheKey};
 use crate::config::Config;
 use crate::error::{DiagnosticCode, Error, Result};
-use crate::generator::event::{Event, Graphviz};
-use crate::generator::Generator;
+use crate::frontend::line_span;
+use crate::generator::event::{Event, Graphviz, GraphvizFormat, Svg};
+use crate::generator::{Generator, JobError};
 use crate::util::ToUnix;
 
 #[derive(Debug)]
 pub struct GraphvizGen<'a> {
     path: PathBuf,
     file: File,
+    /// Directory the rendered graph is cached in
+    cache_dir: PathBuf,
     graphviz: Graphviz<'a>,
     span: Span,
 }
@@ -26,33 +33,8 @@ impl<'a> CodeGenUnit<'a, Graphviz<'a>> for GraphvizGen<'a> {
         gen: &mut Generator<'a, impl Backend<'a>, impl Write>,
     ) -> Result<Self> {
         let Spanned { value: graphviz, span } = graphviz;
-        let mut i = 0;
-        let (file, path) = loop {
-            let p = cfg.out_dir.join(format!("graphviz_{}", i));
-            let res = OpenOptions::new().create_new(true).write(true).open(&p);
-            match res {
-                Ok(file) => break (file, p),
-                Err(ref e) if e.kind() == ErrorKind::AlreadyExists => {
-                    i += 1;
-                    continue;
-                },
-                Err(e) => {
-                    gen.diagnostics()
-                        .bug(DiagnosticCode::TempFileError)
-                        .with_info_label(span, "can't create temporary file for this graphviz code")
-                        .with_info_label(span, format!("cause: {}", e))
-                        .with_note("skipping over it")
-                        .emit();
-                    return Err(Error::Diagnostic);
-                },
-            }
-            // :thonking:
-            #[allow(unreachable_code)]
-            {
-                unreachable!();
-            }
-        };
-        Ok(GraphvizGen { file, path, graphviz, span })
+        let (file, path) = create_temp_file(cfg, gen, span, "graphviz")?;
+        Ok(GraphvizGen { file, path, cache_dir: cfg.cache_dir.clone(), graphviz, span })
     }
 
     fn output_redirect(&mut self) -> Option<&mut dyn Write> {
@@ -64,31 +46,70 @@ impl<'a> CodeGenUnit<'a, Graphviz<'a>> for GraphvizGen<'a> {
         _peek: Option<Spanned<&Event<'a>>>,
     ) -> Result<()> {
         drop(self.file);
-        let Graphviz { label, caption, scale, width, height } = self.graphviz;
-        let out = Command::new("dot")
-            .args(&["-T", "pdf", "-O"])
-            .arg(&self.path)
-            .output()
-            .expect("Error executing `dot` to generate graphviz output");
-        if !out.status.success() {
-            let _ = File::create("dot_stdout.log").map(|mut f| f.write_all(&out.stdout));
-            let _ = File::create("dot_stderr.log").map(|mut f| f.write_all(&out.stderr));
-            // TODO: provide better info about signals
-            // TODO: parse the dot output and provide appropriate error messages
-            gen.diagnostics()
-                .error(DiagnosticCode::GraphvizError)
-                .with_error_label(self.span, "error trying to render this graphviz code block")
-                .with_note(format!("`dot` returned error code {:?}", out.status.code()))
-                .with_note("logs written to dot_stdout.log and dot_stderr.log")
-                .with_note("skipping over it")
-                .emit();
-            return Err(Error::Diagnostic);
+        let Graphviz { label, caption, scale, width, height, engine, format, attributes, code_span } = self.graphviz;
+        let code = fs::read_to_string(&self.path)?;
+        let mut key = CacheKey::new("graphviz").with(engine.to_string()).with(format.to_string());
+        for (name, value) in &attributes {
+            key = key.with(&**name).with(&**value);
         }
+        let key = key.with(&code);
+        // svgs are converted to `graph.svg.pdf` by `Svg::to_pdf_path`
+        let artifact = match format {
+            GraphvizFormat::Pdf => "graph.pdf",
+            GraphvizFormat::Svg => "graph.svg.pdf",
+        };
+        let pdf_path = key.dir(&self.cache_dir).join(artifact);
+        let attributes: Vec<_> = attributes.iter()
+            .map(|(key, value)| format!("-G{}={}", key, value))
+            .collect();
+        let (cache_dir, input, span) = (self.cache_dir, self.path, self.span);
+        let pdf_path = gen.jobs().schedule(span, pdf_path, move || {
+            cache::cached(&cache_dir, key, |dir| {
+                let output = dir.join(format!("graph.{}", format));
+                let out = Command::new("dot")
+                    .arg(format!("-K{}", engine))
+                    .arg(format!("-T{}", format))
+                    .args(&attributes)
+                    .arg("-o")
+                    .arg(&output)
+                    .arg(&input)
+                    .output();
+                let out = match out {
+                    Ok(out) => out,
+                    Err(e) => {
+                        return Err(JobError::new(DiagnosticCode::GraphvizError, "can't render this graphviz code block")
+                            .with_note(format!("error executing `dot`: {}", e))
+                            .with_note("make sure graphviz is installed and in your PATH"));
+                    },
+                };
+                if !out.status.success() {
+                    let stderr = String::from_utf8_lossy(&out.stderr);
+                    let error = JobError::new(DiagnosticCode::GraphvizError, "error trying to render this graphviz code block");
+                    return Err(with_program_error(
+                        error, "dot", parse_error(&stderr), &code, code_span,
+                        || format!("{}:\n{}", out.status, stderr.trim_end()),
+                    ));
+                }
+                match format {
+                    GraphvizFormat::Pdf => Ok(output),
+                    GraphvizFormat::Svg => {
+                        let svg = Svg { label: None, caption: None, title: None, alt_text: None, path: output, scale: None, width: None, height: None };
+                        svg.to_pdf_path(dir).map_err(|e| {
+                            JobError::new(DiagnosticCode::SvgConversionError, "can't convert the svg of this graphviz code block to pdf")
+                                .with_note(e.to_string())
+                        })
+                    },
+                }
+            }).map(drop)
+        });
+
         let out = gen.get_out();
         let inline_fig = InlineEnvironment::new_figure(label, caption);
         inline_fig.write_begin(&mut *out)?;
 
-        write!(out, "\\includegraphics[")?;
+        let pdf_path = pdf_path.to_unix().expect(&format!("non-utf8 path: {:?}", pdf_path));
+        // the render runs after generation and shows a placeholder if it fails
+        write!(out, "\\IfFileExists{{{}}}{{\\includegraphics[", pdf_path)?;
         if let Some(Spanned { value: scale, .. }) = scale {
             write!(out, "scale={},", scale)?;
         }
@@ -98,10 +119,83 @@ impl<'a> CodeGenUnit<'a, Graphviz<'a>> for GraphvizGen<'a> {
         if let Some(Spanned { value: height, .. }) = height {
             write!(out, "height={},", height)?;
         }
-        writeln!(out, "]{{{}.pdf}}", self.path.to_unix()
-            .expect(&format!("non-utf8 path: {:?}", self.path)))?;
+        writeln!(out, "]{{{}}}}}{{\\renderfailed}}", pdf_path)?;
 
         inline_fig.write_end(out)?;
         Ok(())
     }
 }
+
+/// Parses the first error of dot's stderr like `Error: graphviz_0: syntax error in line 3 near '->'`,
+/// returning the line within the graph if it's known and the message.
+fn parse_error(stderr: &str) -> Option<(Option<usize>, String)> {
+    lazy_static! {
+        static ref ERROR: Regex = Regex::new(r"(?m)^Error: (.*)$").unwrap();
+        static ref SYNTAX_ERROR: Regex = Regex::new(r"^(?:.*: )?syntax error in line (\d+)(.*)$").unwrap();
+    }
+    let message = ERROR.captures(stderr)?[1].trim().to_string();
+    match SYNTAX_ERROR.captures(&message) {
+        Some(captures) => {
+            let line = captures[1].parse().ok().filter(|&line| line > 0);
+            Some((line, format!("syntax error{}", &captures[2])))
+        },
+        None => Some((None, message)),
+    }
+}
+
+/// Creates a new file `<name>_<i>` in the output directory to write the code of a code block to.
+pub(super) fn create_temp_file<'a>(
+    cfg: &Config, gen: &mut Generator<'a, impl Backend<'a>, impl Write>, span: Span, name: &str,
+) -> Result<(File, PathBuf)> {
+    let mut i = 0;
+    loop {
+        let p = cfg.out_dir.join(format!("{}_{}", name, i));
+        let res = OpenOptions::new().create_new(true).write(true).open(&p);
+        match res {
+            Ok(file) => return Ok((file, p)),
+            Err(ref e) if e.kind() == ErrorKind::AlreadyExists => i += 1,
+            Err(e) => {
+                gen.diagnostics()
+                    .bug(DiagnosticCode::TempFileError)
+                    .with_info_label(span, format!("can't create temporary file for this {} code", name))
+                    .with_info_label(span, format!("cause: {}", e))
+                    .with_note("skipping over it")
+                    .emit();
+                return Err(Error::Diagnostic);
+            },
+        }
+    }
+}
+
+/// Adds the error parsed from the output of a program rendering code to `error`.
+///
+/// Points at the failing 1-based line of the code if it's known and can be mapped to the source,
+/// otherwise adds the message or the whole `output` as note.
+pub(super) fn with_program_error(
+    error: JobError, program: &str, parsed: Option<(Option<usize>, String)>, code: &str,
+    code_span: Option<Span>, output: impl FnOnce() -> String,
+) -> JobError {
+    let parsed = parsed.map(|(line, message)| {
+        let span = line.and_then(|line| line_span(code, code_span?, line.checked_sub(1)?));
+        (span, message)
+    });
+    match parsed {
+        Some((Some(span), message)) => error.with_error_label(span, message),
+        Some((None, message)) => error.with_note(format!("`{}` failed: {}", program, message)),
+        None => error.with_note(format!("`{}` failed with {}", program, output())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_error;
+
+    #[test]
+    fn test_parse_error() {
+        let stderr = "Error: /tmp/out/graphviz_0: syntax error in line 3 near '->'\n";
+        assert_eq!(parse_error(stderr), Some((Some(3), "syntax error near '->'".to_string())));
+        let stderr = "Warning: node a, port x unrecognized\nError: trouble in init_rank\n";
+        assert_eq!(parse_error(stderr), Some((None, "trouble in init_rank".to_string())));
+        assert_eq!(parse_error("Warning: node a, port x unrecognized"), None);
+    }
+}
@@ -5,7 +5,7 @@ use crate::backend::latex::InlineEnvironment;
 use crate::backend::{Backend, CodeGenUnit};
 use crate::config::Config;
 use crate::error::Result;
-use crate::generator::event::{Equation, Event};
+use crate::generator::event::{Equation, EquationEnvironment, Event};
 use crate::generator::Generator;
 
 #[derive(Debug)]
@@ -29,24 +29,52 @@ impl<'a> CodeGenUnit<'a, ()> for InlineMathGen {
     }
 }
 
+/// Returns the environment and optional inner environment of an equation, e.g. `align*`.
+fn environments(env: EquationEnvironment, numbered: bool) -> (String, Option<&'static str>) {
+    let (outer, inner) = match env {
+        EquationEnvironment::Align => ("align", None),
+        EquationEnvironment::Gather => ("gather", None),
+        EquationEnvironment::Multline => ("multline", None),
+        // split and cases can't be used standalone
+        EquationEnvironment::Split => ("equation", Some("split")),
+        EquationEnvironment::Cases => ("equation", Some("cases")),
+    };
+    let outer = if numbered { outer.to_string() } else { format!("{}*", outer) };
+    (outer, inner)
+}
+
 #[derive(Debug)]
 pub struct EquationGen<'a> {
     inline_fig: InlineEnvironment<'a>,
+    env: (String, Option<&'static str>),
 }
 
-impl<'a> CodeGenUnit<'a, Equation<'a>> for EquationGen<'a> {
-    fn new(
-        _cfg: &Config, eq: Spanned<Equation<'a>>,
+impl<'a> EquationGen<'a> {
+    fn new_with_numbering(
+        eq: Spanned<Equation<'a>>, numbered: bool,
         gen: &mut Generator<'a, impl Backend<'a>, impl Write>,
     ) -> Result<Self> {
-        let Spanned { value: Equation { label, caption }, .. } = eq;
+        let Spanned { value: Equation { label, caption, environment }, .. } = eq;
         let inline_fig = InlineEnvironment::new_figure(label, caption);
+        let env = environments(environment, numbered);
         let out = gen.get_out();
         inline_fig.write_begin(&mut *out)?;
 
-        writeln!(out, "\\begin{{align*}}")?;
+        writeln!(out, "\\begin{{{}}}", env.0)?;
+        if let Some(inner) = env.1 {
+            writeln!(out, "\\begin{{{}}}", inner)?;
+        }
 
-        Ok(EquationGen { inline_fig })
+        Ok(EquationGen { inline_fig, env })
+    }
+}
+
+impl<'a> CodeGenUnit<'a, Equation<'a>> for EquationGen<'a> {
+    fn new(
+        _cfg: &Config, eq: Spanned<Equation<'a>>,
+        gen: &mut Generator<'a, impl Backend<'a>, impl Write>,
+    ) -> Result<Self> {
+        EquationGen::new_with_numbering(eq, false, gen)
     }
 
     fn finish(
@@ -54,38 +82,30 @@ impl<'a> CodeGenUnit<'a, Equation<'a>> for EquationGen<'a> {
         _peek: Option<Spanned<&Event<'a>>>,
     ) -> Result<()> {
         let out = gen.get_out();
-        writeln!(out, "\\end{{align*}}")?;
+        if let Some(inner) = self.env.1 {
+            writeln!(out, "\\end{{{}}}", inner)?;
+        }
+        writeln!(out, "\\end{{{}}}", self.env.0)?;
         self.inline_fig.write_end(out)?;
         Ok(())
     }
 }
 
 #[derive(Debug)]
-pub struct NumberedEquationGen<'a> {
-    inline_fig: InlineEnvironment<'a>,
-}
+pub struct NumberedEquationGen<'a>(EquationGen<'a>);
 
 impl<'a> CodeGenUnit<'a, Equation<'a>> for NumberedEquationGen<'a> {
     fn new(
         _cfg: &Config, eq: Spanned<Equation<'a>>,
         gen: &mut Generator<'a, impl Backend<'a>, impl Write>,
     ) -> Result<Self> {
-        let Spanned { value: Equation { label, caption }, .. } = eq;
-        let inline_fig = InlineEnvironment::new_figure(label, caption);
-        let out = gen.get_out();
-        inline_fig.write_begin(&mut *out)?;
-
-        writeln!(out, "\\begin{{align}}")?;
-        Ok(NumberedEquationGen { inline_fig })
+        EquationGen::new_with_numbering(eq, true, gen).map(NumberedEquationGen)
     }
 
     fn finish(
         self, gen: &'_ mut Generator<'a, impl Backend<'a>, impl Write>,
-        _peek: Option<Spanned<&Event<'a>>>,
+        peek: Option<Spanned<&Event<'a>>>,
     ) -> Result<()> {
-        let out = gen.get_out();
-        writeln!(out, "\\end{{align}}")?;
-        self.inline_fig.write_end(out)?;
-        Ok(())
+        self.0.finish(gen, peek)
     }
 }
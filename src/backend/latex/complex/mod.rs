@@ -2,6 +2,7 @@ mod blockquote;
 mod codeblock;
 mod figure;
 mod footnote_definition;
+mod gnuplot;
 mod graphviz;
 mod header;
 mod inline;
@@ -11,11 +12,13 @@ mod math;
 mod paragraph;
 mod rule;
 mod table;
+mod tikz;
 
 pub use self::blockquote::BlockQuoteGen;
 pub use self::codeblock::CodeBlockGen;
 pub use self::figure::{FigureGen, TableFigureGen};
 pub use self::footnote_definition::FootnoteDefinitionGen;
+pub use self::gnuplot::GnuplotGen;
 pub use self::graphviz::GraphvizGen;
 pub use self::header::{BookHeaderGen, HeaderGen, BeamerHeaderGen};
 pub use self::inline::{InlineCodeGen, InlineEmphasisGen, InlineStrikethroughGen, InlineStrongGen};
@@ -25,3 +28,4 @@ pub use self::math::{EquationGen, InlineMathGen, NumberedEquationGen};
 pub use self::paragraph::ParagraphGen;
 pub use self::rule::BeamerPageBreakGen;
 pub use self::table::{TableCellGen, TableGen, TableHeadGen, TableRowGen};
+pub use self::tikz::TikzGen;
@@ -7,12 +7,13 @@ use crate::backend::latex::InlineEnvironment;
 use crate::backend::{Backend, CodeGenUnit};
 use crate::config::Config;
 use crate::error::Result;
-use crate::generator::event::{Event, Table, Tag};
+use crate::generator::event::{Event, Table, TableCell, TableHead, TableRow, TableStyle, Tag};
 use crate::generator::Generator;
 
 #[derive(Debug)]
 pub struct TableGen<'a> {
-    inline_table: InlineEnvironment<'a>,
+    inline_table: Option<InlineEnvironment<'a>>,
+    style: TableStyle<'a>,
 }
 
 impl<'a> CodeGenUnit<'a, Table<'a>> for TableGen<'a> {
@@ -20,32 +21,66 @@ impl<'a> CodeGenUnit<'a, Table<'a>> for TableGen<'a> {
         _cfg: &'a Config, table: Spanned<Table<'a>>,
         gen: &mut Generator<'a, impl Backend<'a>, impl Write>,
     ) -> Result<Self> {
-        let Spanned { value: Table { label, caption, columns }, .. } = table;
-        let inline_table = InlineEnvironment::new_table(label, caption);
+        let Spanned { value: Table { label, caption, columns, style }, .. } = table;
         let out = gen.get_out();
-        inline_table.write_begin(&mut *out)?;
+        // longtables can't float, their caption is part of the table itself
+        let (inline_table, long_caption) = if style.longtable {
+            (None, Some((label, caption)))
+        } else {
+            let inline_table = InlineEnvironment::new_table(label, caption);
+            inline_table.write_begin(&mut *out)?;
+            (Some(inline_table), None)
+        };
 
-        // TODO: in-cell linebreaks
-        // TODO: merging columns
-        // TODO: merging rows
-        // TODO: easier custom formatting
-        write!(out, "\\begin{{tabularx}}{{\\textwidth}}{{|")?;
+        // the font size and row colors must not leak out of the table
+        if style.font_size.is_some() || style.zebra.is_some() {
+            writeln!(out, "\\begingroup")?;
+        }
+        if let Some(font_size) = &style.font_size {
+            writeln!(out, "\\{}", font_size)?;
+        }
+        if let Some(color) = &style.zebra {
+            writeln!(out, "\\rowcolors{{2}}{{{}}}{{white}}", color)?;
+        }
+
+        let rule = if style.booktabs { "" } else { "|" };
+        write!(out, "\\begin{{tabularx}}{{\\textwidth}}{{{}", rule)?;
         let total_width = columns.len() as f32;
         for (align, width) in columns {
             // https://tex.stackexchange.com/a/249043
             let width = total_width * (width.0 / 100.0);
             write!(out, " >{{\\hsize={:.3}\\hsize}}", width)?;
             match align {
-                Alignment::None => write!(out, "X |")?,
-                Alignment::Left => write!(out, "L |")?,
-                Alignment::Center => write!(out, "C |")?,
-                Alignment::Right => write!(out, "R |")?,
+                Alignment::None => write!(out, "X {}", rule)?,
+                Alignment::Left => write!(out, "L {}", rule)?,
+                Alignment::Center => write!(out, "C {}", rule)?,
+                Alignment::Right => write!(out, "R {}", rule)?,
             }
         }
         write!(out, "}}")?;
         writeln!(out)?;
-        writeln!(out, "\\hline")?;
-        Ok(TableGen { inline_table })
+
+        if let Some((label, caption)) = long_caption {
+            match (&label, &caption) {
+                (_, Some(Spanned { value: caption, .. })) if label.is_some() => write!(out, "\\caption{{{}}}", caption)?,
+                (_, Some(Spanned { value: caption, .. })) => write!(out, "\\caption*{{{}}}", caption)?,
+                (Some(_), None) => write!(out, "\\caption{{}}")?,
+                (None, None) => (),
+            }
+            if let Some(Spanned { value: label, .. }) = &label {
+                write!(out, "\\label{{{}}}", label)?;
+            }
+            if label.is_some() || caption.is_some() {
+                writeln!(out, "\\\\")?;
+            }
+        }
+
+        if style.booktabs {
+            writeln!(out, "\\toprule")?;
+        } else {
+            writeln!(out, "\\hline")?;
+        }
+        Ok(TableGen { inline_table, style })
     }
 
     fn finish(
@@ -53,69 +88,156 @@ impl<'a> CodeGenUnit<'a, Table<'a>> for TableGen<'a> {
         _peek: Option<Spanned<&Event<'a>>>,
     ) -> Result<()> {
         let out = gen.get_out();
+        if self.style.booktabs {
+            writeln!(out, "\\bottomrule")?;
+        }
         writeln!(out, "\\end{{tabularx}}")?;
-        self.inline_table.write_end(out)?;
+        if self.style.font_size.is_some() || self.style.zebra.is_some() {
+            writeln!(out, "\\endgroup")?;
+        }
+        if let Some(inline_table) = self.inline_table {
+            inline_table.write_end(out)?;
+        }
         Ok(())
     }
 }
 
 #[derive(Debug)]
-pub struct TableHeadGen;
+pub struct TableHeadGen {
+    head: TableHead,
+    /// The head of longtables is buffered to repeat it on every page
+    buffer: Vec<u8>,
+}
 
-impl<'a> CodeGenUnit<'a, ()> for TableHeadGen {
+impl<'a> CodeGenUnit<'a, TableHead> for TableHeadGen {
     fn new(
-        _cfg: &'a Config, _: Spanned<()>,
+        _cfg: &'a Config, head: Spanned<TableHead>,
         _gen: &mut Generator<'a, impl Backend<'a>, impl Write>,
     ) -> Result<Self> {
-        Ok(TableHeadGen)
+        Ok(TableHeadGen { head: head.value, buffer: Vec::new() })
+    }
+
+    fn output_redirect(&mut self) -> Option<&mut dyn Write> {
+        if self.head.longtable {
+            Some(&mut self.buffer)
+        } else {
+            None
+        }
     }
 
     fn finish(
         self, gen: &mut Generator<'a, impl Backend<'a>, impl Write>,
         _peek: Option<Spanned<&Event<'a>>>,
     ) -> Result<()> {
-        writeln!(gen.get_out(), "\\\\ \\thickhline")?;
+        let out = gen.get_out();
+        let rule = if self.head.booktabs { "\\midrule" } else { "\\thickhline" };
+        if !self.head.longtable {
+            writeln!(out, "\\\\ {}", rule)?;
+            return Ok(());
+        }
+        out.write_all(&self.buffer)?;
+        writeln!(out, "\\\\ {}", rule)?;
+        writeln!(out, "\\endfirsthead")?;
+        let top = if self.head.booktabs { "\\toprule" } else { "\\hline" };
+        writeln!(out, "{}", top)?;
+        out.write_all(&self.buffer)?;
+        writeln!(out, "\\\\ {}", rule)?;
+        writeln!(out, "\\endhead")?;
         Ok(())
     }
 }
 
 #[derive(Debug)]
-pub struct TableRowGen;
+pub struct TableRowGen {
+    rules_below: Vec<bool>,
+}
 
-impl<'a> CodeGenUnit<'a, ()> for TableRowGen {
+impl<'a> CodeGenUnit<'a, TableRow> for TableRowGen {
     fn new(
-        _cfg: &'a Config, _: Spanned<()>,
+        _cfg: &'a Config, row: Spanned<TableRow>,
         _gen: &mut Generator<'a, impl Backend<'a>, impl Write>,
     ) -> Result<Self> {
-        Ok(TableRowGen)
+        let Spanned { value: TableRow { rules_below }, .. } = row;
+        Ok(TableRowGen { rules_below })
     }
 
     fn finish(
         self, gen: &mut Generator<'a, impl Backend<'a>, impl Write>,
         _peek: Option<Spanned<&Event<'a>>>,
     ) -> Result<()> {
-        writeln!(gen.get_out(), "\\\\ \\hline")?;
+        let out = gen.get_out();
+        if self.rules_below.iter().all(|&rule| rule) {
+            writeln!(out, "\\\\ \\hline")?;
+            return Ok(());
+        }
+        // don't cross cells spanning into the next row
+        write!(out, "\\\\")?;
+        let mut start = None;
+        for (i, &rule) in self.rules_below.iter().chain(Some(&false)).enumerate() {
+            match (start, rule) {
+                (None, true) => start = Some(i),
+                (Some(s), false) => {
+                    write!(out, " \\cline{{{}-{}}}", s + 1, i)?;
+                    start = None;
+                },
+                _ => (),
+            }
+        }
+        writeln!(out)?;
         Ok(())
     }
 }
 
 #[derive(Debug)]
-pub struct TableCellGen;
+pub struct TableCellGen {
+    colspan: usize,
+    rowspan: usize,
+}
 
-impl<'a> CodeGenUnit<'a, ()> for TableCellGen {
+impl<'a> CodeGenUnit<'a, TableCell> for TableCellGen {
     fn new(
-        _cfg: &'a Config, _: Spanned<()>,
-        _gen: &mut Generator<'a, impl Backend<'a>, impl Write>,
+        _cfg: &'a Config, cell: Spanned<TableCell>,
+        gen: &mut Generator<'a, impl Backend<'a>, impl Write>,
     ) -> Result<Self> {
-        Ok(TableCellGen)
+        let Spanned { value: TableCell { column, colspan, rowspan, covered, alignment, width, booktabs }, .. } = cell;
+        let out = gen.get_out();
+        if colspan > 1 {
+            let left_rule = if column == 0 && !booktabs { "|" } else { "" };
+            let right_rule = if booktabs { "" } else { "|" };
+            let align = match alignment {
+                Alignment::None => "",
+                Alignment::Left => "\\raggedright",
+                Alignment::Center => "\\centering",
+                Alignment::Right => "\\raggedleft",
+            };
+            // the paddings and rules between the merged columns belong to the cell
+            write!(
+                out,
+                "\\multicolumn{{{}}}{{{}>{{{}\\arraybackslash}}m{{\\dimexpr {:.3}\\linewidth-2\\tabcolsep\\relax}}{}}}{{",
+                colspan, left_rule, align, width.0 / 100.0, right_rule,
+            )?;
+        }
+        // covered cells are empty, their content is written in the row of the first cell
+        let rowspan = if covered { 1 } else { rowspan };
+        if rowspan > 1 {
+            write!(out, "\\multirow{{{}}}{{=}}{{", rowspan)?;
+        }
+        Ok(TableCellGen { colspan, rowspan })
     }
 
     fn finish(
         self, gen: &mut Generator<'a, impl Backend<'a>, impl Write>,
         peek: Option<Spanned<&Event<'a>>>,
     ) -> Result<()> {
-        if let Event::Start(Tag::TableCell) = peek.unwrap().value {
-            write!(gen.get_out(), " & ")?;
+        let out = gen.get_out();
+        if self.rowspan > 1 {
+            write!(out, "}}")?;
+        }
+        if self.colspan > 1 {
+            write!(out, "}}")?;
+        }
+        if let Event::Start(Tag::TableCell(_)) = peek.unwrap().value {
+            write!(out, " & ")?;
         }
         Ok(())
     }
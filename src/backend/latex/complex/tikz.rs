@@ -0,0 +1,55 @@
+use std::io::Write;
+use diagnostic::Spanned;
+
+use crate::backend::latex::InlineEnvironment;
+use crate::backend::{Backend, CodeGenUnit};
+use crate::config::Config;
+use crate::error::Result;
+use crate::generator::event::{Event, Tikz};
+use crate::generator::Generator;
+
+/// Inlines the picture as `tikzpicture` into the document.
+#[derive(Debug)]
+pub struct TikzGen<'a> {
+    inline_fig: InlineEnvironment<'a>,
+    /// Number of boxes opened for scale, width and height
+    boxes: usize,
+}
+
+impl<'a> CodeGenUnit<'a, Tikz<'a>> for TikzGen<'a> {
+    fn new(
+        _cfg: &Config, tikz: Spanned<Tikz<'a>>,
+        gen: &mut Generator<'a, impl Backend<'a>, impl Write>,
+    ) -> Result<Self> {
+        let Tikz { label, caption, scale, width, height } = tikz.value;
+        let out = gen.get_out();
+        let inline_fig = InlineEnvironment::new_figure(label, caption);
+        inline_fig.write_begin(&mut *out)?;
+
+        // like other figures, the picture is resized as a whole including its text
+        let mut boxes = 0;
+        if let Some(Spanned { value: scale, .. }) = &scale {
+            write!(out, "\\scalebox{{{}}}{{", scale)?;
+            boxes += 1;
+        }
+        if width.is_some() || height.is_some() {
+            let width = width.as_ref().map_or("!", |w| &*w.value);
+            let height = height.as_ref().map_or("!", |h| &*h.value);
+            write!(out, "\\resizebox{{{}}}{{{}}}{{", width, height)?;
+            boxes += 1;
+        }
+        writeln!(out, "\\begin{{tikzpicture}}")?;
+        Ok(TikzGen { inline_fig, boxes })
+    }
+
+    fn finish(
+        self, gen: &mut Generator<'a, impl Backend<'a>, impl Write>,
+        _peek: Option<Spanned<&Event<'a>>>,
+    ) -> Result<()> {
+        let out = gen.get_out();
+        write!(out, "\\end{{tikzpicture}}")?;
+        writeln!(out, "{}", "}".repeat(self.boxes))?;
+        self.inline_fig.write_end(out)?;
+        Ok(())
+    }
+}
@@ -14,6 +14,7 @@ pub struct Article;
 impl<'a> Backend<'a> for Article {
     type Text = latex::TextGen;
     type Latex = latex::LatexGen;
+    type Comment = latex::CommentGen;
     type FootnoteReference = latex::FootnoteReferenceGen;
     type BiberReferences = latex::BiberReferencesGen;
     type Url = latex::UrlGen;
@@ -61,6 +62,8 @@ impl<'a> Backend<'a> for Article {
     type Equation = latex::EquationGen<'a>;
     type NumberedEquation = latex::NumberedEquationGen<'a>;
     type Graphviz = latex::GraphvizGen<'a>;
+    type Gnuplot = latex::GnuplotGen<'a>;
+    type Tikz = latex::TikzGen<'a>;
 
     fn new() -> Self {
         Article
@@ -119,6 +119,7 @@ fn check_level(level: i32, span: Span, diagnostics: &Diagnostics) -> Result<()>
 impl<'a> Backend<'a> for Beamer {
     type Text = latex::TextGen;
     type Latex = latex::LatexGen;
+    type Comment = latex::CommentGen;
     type FootnoteReference = latex::FootnoteReferenceGen;
     type BiberReferences = latex::BiberReferencesGen;
     type Url = latex::UrlGen;
@@ -166,6 +167,8 @@ impl<'a> Backend<'a> for Beamer {
     type Equation = latex::EquationGen<'a>;
     type NumberedEquation = latex::NumberedEquationGen<'a>;
     type Graphviz = latex::GraphvizGen<'a>;
+    type Gnuplot = latex::GnuplotGen<'a>;
+    type Tikz = latex::TikzGen<'a>;
 
     fn new() -> Self {
         Beamer {
@@ -175,7 +178,7 @@ impl<'a> Backend<'a> for Beamer {
 
     fn gen_preamble(&mut self, cfg: &Config, out: &mut impl Write, diagnostics: &'a Diagnostics) -> FatalResult<()> {
         // Beamer already loads internally color, hyperref, xcolor. Correct their options.
-        preamble::write_documentclass(cfg, out, "beamer", "color={usenames,dvipsnames},xcolor={usenames,dvipsnames},hyperref={pdfusetitle},")?;
+        preamble::write_documentclass(cfg, out, "beamer", "color={usenames,dvipsnames},xcolor={usenames,dvipsnames,table},hyperref={pdfusetitle},")?;
         writeln!(out, "\\usetheme{{{}}}", cfg.beamertheme)?;
         writeln!(out)?;
 
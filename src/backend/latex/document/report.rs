@@ -13,6 +13,7 @@ pub struct Report;
 impl<'a> Backend<'a> for Report {
     type Text = latex::TextGen;
     type Latex = latex::LatexGen;
+    type Comment = latex::CommentGen;
     type FootnoteReference = latex::FootnoteReferenceGen;
     type BiberReferences = latex::BiberReferencesGen;
     type Url = latex::UrlGen;
@@ -60,6 +61,8 @@ impl<'a> Backend<'a> for Report {
     type Equation = latex::EquationGen<'a>;
     type NumberedEquation = latex::NumberedEquationGen<'a>;
     type Graphviz = latex::GraphvizGen<'a>;
+    type Gnuplot = latex::GnuplotGen<'a>;
+    type Tikz = latex::TikzGen<'a>;
 
     fn new() -> Self {
         Report
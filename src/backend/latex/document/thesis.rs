@@ -18,6 +18,7 @@ pub struct Thesis;
 impl<'a> Backend<'a> for Thesis {
     type Text = latex::TextGen;
     type Latex = latex::LatexGen;
+    type Comment = latex::CommentGen;
     type FootnoteReference = latex::FootnoteReferenceGen;
     type BiberReferences = latex::BiberReferencesGen;
     type Url = latex::UrlGen;
@@ -65,6 +66,8 @@ impl<'a> Backend<'a> for Thesis {
     type Equation = latex::EquationGen<'a>;
     type NumberedEquation = latex::NumberedEquationGen<'a>;
     type Graphviz = latex::GraphvizGen<'a>;
+    type Gnuplot = latex::GnuplotGen<'a>;
+    type Tikz = latex::TikzGen<'a>;
 
     fn new() -> Self {
         Thesis
@@ -138,5 +141,6 @@ fn gen_abstract(path: PathBuf, abstract_name: &str, cfg: &Config, out: &mut impl
     let markdown = Spanned::new(markdown, Span::new(fileid, 0, markdown.len()));
     let events = gen.get_events(markdown, context);
     gen.generate_body(events)?;
+    gen.run_jobs();
     Ok(())
 }
@@ -0,0 +1,507 @@
+//! Built-in syntax highlighting of code blocks, emitting pre-coloured LaTeX for `fancyvrb`.
+//!
+//! Unlike `listings`, this doesn't depend on the LaTeX installation knowing the language.
+//! Each language is described by a simple grammar of comments, strings and keywords.
+
+use std::fmt::Write;
+
+/// Class of a highlighted token, rendered as `\HD<class>{…}` defined in the preamble.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Text,
+    Keyword,
+    Type,
+    Literal,
+    Number,
+    String,
+    Comment,
+    /// Preprocessor directives, attributes, annotations and decorators
+    Meta,
+}
+
+impl TokenKind {
+    fn command(self) -> Option<&'static str> {
+        match self {
+            TokenKind::Text => None,
+            TokenKind::Keyword => Some("HDkeyword"),
+            TokenKind::Type => Some("HDtype"),
+            TokenKind::Literal => Some("HDliteral"),
+            TokenKind::Number => Some("HDnumber"),
+            TokenKind::String => Some("HDstring"),
+            TokenKind::Comment => Some("HDcomment"),
+            TokenKind::Meta => Some("HDmeta"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Grammar {
+    names: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    /// Quotes of string literals, longer ones first like `"""` before `"`
+    quotes: &'static [&'static str],
+    multiline_strings: bool,
+    /// If `'` starts char literals, but can also be a lifetime like `'a`
+    char_literals: bool,
+    /// Prefixes of preprocessor directives, attributes and annotations
+    meta: &'static [&'static str],
+    keywords: &'static [&'static str],
+    types: &'static [&'static str],
+    literals: &'static [&'static str],
+    /// If identifiers starting with an uppercase letter are types
+    uppercase_types: bool,
+}
+
+const C_KEYWORDS: &[&str] = &[
+    "auto", "break", "case", "const", "continue", "default", "do", "else", "enum", "extern",
+    "for", "goto", "if", "inline", "register", "restrict", "return", "sizeof", "static", "struct",
+    "switch", "typedef", "union", "volatile", "while",
+];
+const C_TYPES: &[&str] = &[
+    "char", "double", "float", "int", "long", "short", "signed", "unsigned", "void", "size_t",
+    "ssize_t", "bool", "int8_t", "int16_t", "int32_t", "int64_t", "uint8_t", "uint16_t",
+    "uint32_t", "uint64_t",
+];
+
+const GRAMMARS: &[Grammar] = &[
+    Grammar {
+        names: &["rust", "rs"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &["\""],
+        multiline_strings: true,
+        char_literals: true,
+        meta: &["#!", "#"],
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+            "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "type",
+            "unsafe", "use", "where", "while",
+        ],
+        types: &[
+            "bool", "char", "str", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32",
+            "i64", "i128", "isize", "f32", "f64",
+        ],
+        literals: &["true", "false"],
+        uppercase_types: true,
+    },
+    Grammar {
+        names: &["typescript", "ts", "javascript", "js", "tsx", "jsx"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &["`", "\"", "'"],
+        multiline_strings: true,
+        char_literals: false,
+        meta: &["@"],
+        keywords: &[
+            "abstract", "as", "async", "await", "break", "case", "catch", "class", "const",
+            "continue", "debugger", "declare", "default", "delete", "do", "else", "enum", "export",
+            "extends", "finally", "for", "from", "function", "get", "if", "implements", "import",
+            "in", "instanceof", "interface", "keyof", "let", "namespace", "new", "of", "private",
+            "protected", "public", "readonly", "return", "set", "static", "super", "switch", "this",
+            "throw", "try", "type", "typeof", "var", "void", "while", "with", "yield",
+        ],
+        types: &["any", "boolean", "never", "number", "object", "string", "symbol", "unknown", "bigint"],
+        literals: &["true", "false", "null", "undefined", "NaN", "Infinity"],
+        uppercase_types: true,
+    },
+    Grammar {
+        names: &["kotlin", "kt", "kts"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &["\"\"\"", "\"", "'"],
+        multiline_strings: true,
+        char_literals: false,
+        meta: &["@"],
+        keywords: &[
+            "abstract", "as", "break", "by", "catch", "class", "companion", "const", "constructor",
+            "continue", "data", "do", "else", "enum", "fun", "for", "if", "import", "in", "init",
+            "interface", "internal", "is", "lateinit", "object", "open", "operator", "override",
+            "package", "private", "protected", "public", "return", "sealed", "super", "suspend",
+            "this", "throw", "try", "typealias", "val", "var", "when", "while",
+        ],
+        types: &[],
+        literals: &["true", "false", "null"],
+        uppercase_types: true,
+    },
+    Grammar {
+        names: &["java"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &["\"\"\"", "\"", "'"],
+        multiline_strings: true,
+        char_literals: false,
+        meta: &["@"],
+        keywords: &[
+            "abstract", "assert", "break", "case", "catch", "class", "continue", "default", "do",
+            "else", "enum", "extends", "final", "finally", "for", "if", "implements", "import",
+            "instanceof", "interface", "native", "new", "package", "private", "protected",
+            "public", "return", "static", "super", "switch", "synchronized", "this", "throw",
+            "throws", "transient", "try", "var", "volatile", "while",
+        ],
+        types: &["boolean", "byte", "char", "double", "float", "int", "long", "short", "void"],
+        literals: &["true", "false", "null"],
+        uppercase_types: true,
+    },
+    Grammar {
+        names: &["c", "h"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &["\"", "'"],
+        multiline_strings: false,
+        char_literals: false,
+        meta: &["#"],
+        keywords: C_KEYWORDS,
+        types: C_TYPES,
+        literals: &["true", "false", "NULL"],
+        uppercase_types: false,
+    },
+    Grammar {
+        names: &["c++", "cpp", "cxx", "cc", "hpp"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &["\"", "'"],
+        multiline_strings: false,
+        char_literals: false,
+        meta: &["#"],
+        keywords: &[
+            "auto", "break", "case", "catch", "class", "const", "constexpr", "continue",
+            "default", "delete", "do", "else", "enum", "explicit", "extern", "for", "friend",
+            "goto", "if", "inline", "namespace", "new", "noexcept", "operator", "override",
+            "private", "protected", "public", "return", "sizeof", "static", "struct", "switch",
+            "template", "this", "throw", "try", "typedef", "typename", "union", "using", "virtual",
+            "volatile", "while",
+        ],
+        types: C_TYPES,
+        literals: &["true", "false", "nullptr", "NULL"],
+        uppercase_types: false,
+    },
+    Grammar {
+        names: &["go", "golang"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &["`", "\"", "'"],
+        multiline_strings: true,
+        char_literals: false,
+        meta: &[],
+        keywords: &[
+            "break", "case", "chan", "const", "continue", "default", "defer", "else",
+            "fallthrough", "for", "func", "go", "goto", "if", "import", "interface", "map",
+            "package", "range", "return", "select", "struct", "switch", "type", "var",
+        ],
+        types: &[
+            "bool", "byte", "error", "float32", "float64", "int", "int8", "int16", "int32",
+            "int64", "rune", "string", "uint", "uint8", "uint16", "uint32", "uint64", "uintptr",
+        ],
+        literals: &["true", "false", "nil", "iota"],
+        uppercase_types: false,
+    },
+    Grammar {
+        names: &["python", "py"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &["\"\"\"", "'''", "\"", "'"],
+        multiline_strings: true,
+        char_literals: false,
+        meta: &["@"],
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+            "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in",
+            "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+            "with", "yield",
+        ],
+        types: &["int", "float", "str", "bool", "list", "dict", "set", "tuple", "bytes"],
+        literals: &["True", "False", "None"],
+        uppercase_types: false,
+    },
+    Grammar {
+        names: &["bash", "sh", "shell", "zsh"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &["\"", "'"],
+        multiline_strings: true,
+        char_literals: false,
+        meta: &[],
+        keywords: &[
+            "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+            "in", "local", "return", "then", "until", "while",
+        ],
+        types: &[],
+        literals: &["true", "false"],
+        uppercase_types: false,
+    },
+    Grammar {
+        names: &["toml"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &["\"\"\"", "'''", "\"", "'"],
+        multiline_strings: true,
+        char_literals: false,
+        meta: &["["],
+        keywords: &[],
+        types: &[],
+        literals: &["true", "false"],
+        uppercase_types: false,
+    },
+    Grammar {
+        names: &["json"],
+        line_comments: &[],
+        block_comment: None,
+        quotes: &["\""],
+        multiline_strings: false,
+        char_literals: false,
+        meta: &[],
+        keywords: &[],
+        types: &[],
+        literals: &["true", "false", "null"],
+        uppercase_types: false,
+    },
+    Grammar {
+        names: &["yaml", "yml"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &["\"", "'"],
+        multiline_strings: false,
+        char_literals: false,
+        meta: &[],
+        keywords: &[],
+        types: &[],
+        literals: &["true", "false", "null", "yes", "no"],
+        uppercase_types: false,
+    },
+];
+
+fn grammar(language: &str) -> Option<&'static Grammar> {
+    let language = language.to_lowercase();
+    GRAMMARS.iter().find(|grammar| grammar.names.contains(&language.as_str()))
+}
+
+/// Returns if the built-in highlighter knows the given language.
+pub fn supports(language: &str) -> bool {
+    grammar(language).is_some()
+}
+
+/// Splits the code into highlighted tokens. Returns `None` if the language is unknown.
+pub fn tokenize<'c>(language: &str, code: &'c str) -> Option<Vec<(TokenKind, &'c str)>> {
+    let grammar = grammar(language)?;
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    // start of the current plain text token
+    let mut text = 0;
+    let push = |tokens: &mut Vec<_>, text: &mut usize, start: usize, end: usize, kind| {
+        if *text < start {
+            tokens.push((TokenKind::Text, &code[*text..start]));
+        }
+        tokens.push((kind, &code[start..end]));
+        *text = end;
+    };
+    while i < code.len() {
+        let rest = &code[i..];
+        let c = rest.chars().next().unwrap();
+        let line_end = rest.find('\n').map_or(code.len(), |end| i + end);
+
+        if grammar.line_comments.iter().any(|start| rest.starts_with(start)) {
+            push(&mut tokens, &mut text, i, line_end, TokenKind::Comment);
+            i = line_end;
+        } else if let Some((start, end)) = grammar.block_comment.filter(|(start, _)| rest.starts_with(start)) {
+            let len = rest[start.len()..].find(end).map_or(rest.len(), |len| start.len() + len + end.len());
+            push(&mut tokens, &mut text, i, i + len, TokenKind::Comment);
+            i += len;
+        } else if let Some(len) = grammar.meta.iter()
+            .filter(|meta| rest.starts_with(*meta))
+            // TOML tables only at the start of a line
+            .filter(|&&meta| meta != "[" || code[..i].rsplit('\n').next().unwrap().trim().is_empty())
+            .find_map(|meta| meta_len(rest, meta, line_end - i))
+        {
+            push(&mut tokens, &mut text, i, i + len, TokenKind::Meta);
+            i += len;
+        } else if let Some(quote) = grammar.quotes.iter().find(|quote| rest.starts_with(*quote)) {
+            let len = string_len(rest, quote, grammar.multiline_strings);
+            push(&mut tokens, &mut text, i, i + len, TokenKind::String);
+            i += len;
+        } else if c == '\'' && grammar.char_literals {
+            // char literal `'a'` / `'\n'` or lifetime `'a`
+            let len = match rest[1..].chars().next() {
+                Some('\\') => string_len(rest, "'", false),
+                Some(c) if rest[1 + c.len_utf8()..].starts_with('\'') => 2 + c.len_utf8(),
+                _ => 1 + ident_len(&rest[1..]),
+            };
+            let kind = if rest[..len].ends_with('\'') && len > 2 { TokenKind::String } else { TokenKind::Type };
+            push(&mut tokens, &mut text, i, i + len, kind);
+            i += len;
+        } else if c.is_ascii_digit() {
+            let len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.')).unwrap_or(rest.len());
+            // don't include the range operator `..` in the number
+            let len = rest[..len].find("..").unwrap_or(len);
+            push(&mut tokens, &mut text, i, i + len, TokenKind::Number);
+            i += len;
+        } else if c.is_alphabetic() || c == '_' {
+            let len = ident_len(rest);
+            let ident = &rest[..len];
+            let kind = if grammar.keywords.contains(&ident) {
+                TokenKind::Keyword
+            } else if grammar.literals.contains(&ident) {
+                TokenKind::Literal
+            } else if grammar.types.contains(&ident)
+                || (grammar.uppercase_types && ident.starts_with(char::is_uppercase))
+            {
+                TokenKind::Type
+            } else {
+                TokenKind::Text
+            };
+            if kind != TokenKind::Text {
+                push(&mut tokens, &mut text, i, i + len, kind);
+            }
+            i += len;
+        } else {
+            i += c.len_utf8();
+        }
+    }
+    if text < code.len() {
+        tokens.push((TokenKind::Text, &code[text..]));
+    }
+    Some(tokens)
+}
+
+fn ident_len(s: &str) -> usize {
+    s.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(s.len())
+}
+
+/// Length of a string literal starting with the given quote, including both quotes.
+fn string_len(s: &str, quote: &str, multiline: bool) -> usize {
+    let mut chars = s.char_indices().skip(quote.chars().count());
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        } else if s[i..].starts_with(quote) {
+            return i + quote.len();
+        } else if c == '\n' && !multiline {
+            return i;
+        }
+    }
+    s.len()
+}
+
+/// Length of a meta token like `#[derive(Debug)]`, `#include <stdio.h>`, `@Override` or the
+/// TOML table `[package]`, or `None` if it isn't one.
+fn meta_len(s: &str, meta: &str, line_len: usize) -> Option<usize> {
+    let after = &s[meta.len()..];
+    if meta == "[" || after.starts_with('[') {
+        // attribute / table: until the matching bracket
+        let mut depth = 0;
+        for (i, c) in s[..line_len].char_indices() {
+            match c {
+                '[' => depth += 1,
+                ']' if depth == 1 => return Some(i + 1),
+                ']' => depth -= 1,
+                _ => (),
+            }
+        }
+        Some(line_len)
+    } else if meta == "#" && after.starts_with(char::is_alphabetic) {
+        // preprocessor directive
+        Some(line_len)
+    } else if after.starts_with(char::is_alphabetic) {
+        Some(meta.len() + ident_len(after))
+    } else {
+        None
+    }
+}
+
+/// Writes the tokens as LaTeX for a `Verbatim` environment with `commandchars=\\\{\}`.
+///
+/// Each line starts with `\HDhighlight` if its printed number (`firstline` for the first line)
+/// is within one of the `highlight` ranges. With `mathescape`, text between `$` is kept as is.
+pub fn write_latex(
+    tokens: &[(TokenKind, &str)], firstline: usize, highlight: &[(usize, usize)], mathescape: bool,
+    out: &mut String,
+) {
+    let mut in_math = false;
+    let mut latex = String::new();
+    for &(kind, text) in tokens {
+        for (i, part) in text.split('\n').enumerate() {
+            if i > 0 {
+                latex.push('\n');
+            }
+            if part.is_empty() {
+                continue;
+            }
+            match kind.command() {
+                Some(command) => {
+                    write!(latex, "\\{}{{{}}}", command, escape(part, mathescape, &mut in_math)).unwrap()
+                },
+                None => latex.push_str(&escape(part, mathescape, &mut in_math)),
+            }
+        }
+    }
+    for (i, line) in latex.lines().enumerate() {
+        let number = firstline + i;
+        if highlight.iter().any(|&(start, end)| start <= number && number <= end) {
+            out.push_str("\\HDhighlight{}");
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+}
+
+fn escape(s: &str, mathescape: bool, in_math: &mut bool) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '$' if mathescape => {
+                *in_math = !*in_math;
+                escaped.push(c);
+            },
+            c if *in_math => escaped.push(c),
+            '\\' => escaped.push_str("\\HDbs{}"),
+            '{' => escaped.push_str("\\HDob{}"),
+            '}' => escaped.push_str("\\HDcb{}"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::{tokenize, write_latex, TokenKind};
+
+    #[test]
+    fn test_tokenize_rust() {
+        let tokens = tokenize("rust", "#[derive(Debug)]\nfn foo<'a>(x: &'a str) -> char { 'x' } // end").unwrap();
+        let highlighted: Vec<_> = tokens.into_iter().filter(|&(kind, _)| kind != TokenKind::Text).collect();
+        assert_eq!(highlighted, vec![
+            (TokenKind::Meta, "#[derive(Debug)]"),
+            (TokenKind::Keyword, "fn"),
+            (TokenKind::Type, "'a"),
+            (TokenKind::Type, "'a"),
+            (TokenKind::Type, "str"),
+            (TokenKind::Type, "char"),
+            (TokenKind::String, "'x'"),
+            (TokenKind::Comment, "// end"),
+        ]);
+    }
+
+    #[test]
+    fn test_tokenize_strings() {
+        let tokens = tokenize("python", "x = \"a\\\"b\" # c\ny = 1.5").unwrap();
+        assert_eq!(tokens, vec![
+            (TokenKind::Text, "x = "),
+            (TokenKind::String, "\"a\\\"b\""),
+            (TokenKind::Text, " "),
+            (TokenKind::Comment, "# c"),
+            (TokenKind::Text, "\ny = "),
+            (TokenKind::Number, "1.5"),
+        ]);
+        assert!(tokenize("brainfuck", "+").is_none());
+    }
+
+    #[test]
+    fn test_write_latex() {
+        let tokens = tokenize("toml", "[package]\nname = \"{x}\"\n").unwrap();
+        let mut out = String::new();
+        write_latex(&tokens, 1, &[(2, 2)], false, &mut out);
+        assert_eq!(out, "\\HDmeta{[package]}\n\\HDhighlight{}name = \\HDstring{\"\\HDob{}x\\HDcb{}\"}\n");
+    }
+}
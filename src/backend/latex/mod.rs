@@ -4,9 +4,11 @@ use diagnostic::Spanned;
 
 mod complex;
 mod document;
+mod highlight;
 mod preamble;
 mod replace;
 mod simple;
+mod typography;
 
 pub use self::document::{Article, Beamer, BeamerFrameEvent, Report, Thesis};
 
@@ -14,6 +16,7 @@ use self::simple::{
     AppendixGen,
     BiberReferencesGen,
     BibliographyGen,
+    CommentGen,
     FootnoteReferenceGen,
     SoftBreakGen,
     HardBreakGen,
@@ -42,6 +45,7 @@ use self::complex::{
     EquationGen,
     FigureGen,
     FootnoteDefinitionGen,
+    GnuplotGen,
     GraphvizGen,
     HeaderGen,
     BeamerHeaderGen,
@@ -61,6 +65,7 @@ use self::complex::{
     TableGen,
     TableHeadGen,
     TableRowGen,
+    TikzGen,
     UrlWithContentGen,
 };
 
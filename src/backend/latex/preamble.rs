@@ -62,8 +62,9 @@ pub fn write_packages(cfg: &Config, out: &mut impl Write) -> Result<()> {
     // TODO: use minted instead of lstlistings?
     // TODO: do we want scrhack?
     writeln!(out, "\\usepackage{{listings}}")?;
+    writeln!(out, "\\usepackage{{fancyvrb}}")?;
     writeln!(out, "\\usepackage[usenames, dvipsnames]{{color}}")?;
-    writeln!(out, "\\usepackage{{xcolor}}")?;
+    writeln!(out, "\\usepackage[table]{{xcolor}}")?;
     writeln!(out, "\\usepackage{{pdfpages}}")?;
     writeln!(out, "\\usepackage{{environ}}")?;
     writeln!(out, "\\usepackage{{amssymb}}")?;
@@ -73,6 +74,7 @@ pub fn write_packages(cfg: &Config, out: &mut impl Write) -> Result<()> {
     writeln!(out, "\\usepackage[normalem]{{ulem}}")?;
     // TODO: graphicspath (probably not needed due to our own file resolution system (resolve))
     writeln!(out, "\\usepackage{{graphicx}}")?;
+    writeln!(out, "\\usepackage{{tikz}}")?;
     writeln!(out, "\\usepackage{{transparent}}")?;
     writeln!(out, "\\usepackage[final]{{microtype}}")?;
     writeln!(out, "\\usepackage[pdfusetitle]{{hyperref}}")?;
@@ -86,6 +88,8 @@ pub fn write_packages(cfg: &Config, out: &mut impl Write) -> Result<()> {
     writeln!(out, "\\usepackage{{tabularx}}")?;
     // auto-wrap long tables at page boundaries
     writeln!(out, "\\usepackage{{ltablex}}")?;
+    writeln!(out, "\\usepackage{{multirow}}")?;
+    writeln!(out, "\\usepackage{{booktabs}}")?;
     writeln!(out, "\\usepackage{{grffile}}")?;
     let fancy = [
         ("lhead", &cfg.lhead, &cfg.lhead_even), ("chead", &cfg.chead, &cfg.chead_even), ("rhead", &cfg.rhead, &cfg.rhead_even),
@@ -114,6 +118,9 @@ pub fn write_packages(cfg: &Config, out: &mut impl Write) -> Result<()> {
             writeln!(out, "\\fancypagestyle{{empty}}{{\\pagestyle{{fancy}}}}")?;
         }
     }
+
+    // math macros
+    cfg.math.write_latex_macros(&mut *out)?;
     writeln!(out)?;
     Ok(())
 }
@@ -127,10 +134,13 @@ pub fn write_fixes(cfg: &Config, out: &mut impl Write) -> Result<()> {
     writeln!(out, "{}", LST_DEFINE_ASM)?;
     writeln!(out, "{}", LST_DEFINE_RUST)?;
     writeln!(out, "{}", LST_DEFINE_JS)?;
+    writeln!(out, "{}", LST_HIGHLIGHT_LINES)?;
+    writeln!(out, "{}", BUILTIN_HIGHLIGHTING)?;
     writeln!(out, "{}", THICKHLINE)?;
     writeln!(out, "{}", AQUOTE)?;
     writeln!(out, "{}", FIX_INCLUDEGRAPHICS)?;
     writeln!(out, "{}", IMAGE_WITH_TEXT)?;
+    writeln!(out, "{}", RENDER_FAILED)?;
     writeln!(out, "{}", SCALE_TIKZ_PICTURE_TO_WIDTH)?;
     writeln!(out, "{}", TABULARX)?;
     // TODO: figures inline? https://tex.stackexchange.com/a/11342 last codeblock
@@ -373,6 +383,42 @@ pub const AQUOTE: &str = r#"
   {\signed{\usebox\mybox}\end{quote}}
 "#;
 
+// background of single lines, like the lstlinebgrd package
+pub const LST_HIGHLIGHT_LINES: &str = r#"
+\colorlet{lsthighlight}{yellow!30}
+\makeatletter
+\def\heradoc@highlightlines{}
+\lst@Key{highlightlines}{}{\def\heradoc@highlightlines{#1}}
+% ranges like `3-3,5-7`, such that large ranges don't need to be expanded
+\def\heradoc@highlightrange#1-#2\@nil{%
+  \ifnum\value{lstnumber}<#1\relax\else\ifnum\value{lstnumber}>#2\relax\else
+    \rlap{\color{lsthighlight}\rule[-.3\baselineskip]{\linewidth}{\baselineskip}}%
+  \fi\fi}
+\lst@AddToHook{EveryPar}{%
+  \@for\heradoc@range:=\heradoc@highlightlines\do{%
+    \expandafter\heradoc@highlightrange\heradoc@range\@nil}}
+\makeatother
+"#;
+
+// used by the built-in highlighter within `Verbatim`, colors matching LSTSET
+pub const BUILTIN_HIGHLIGHTING: &str = r#"
+\newcommand*{\HDbs}{\char`\\}
+\newcommand*{\HDob}{\char`\{}
+\newcommand*{\HDcb}{\char`\}}
+\newcommand*{\HDkeyword}[1]{\textbf{\textcolor{OliveGreen}{#1}}}
+\newcommand*{\HDtype}[1]{\textcolor{Cyan}{#1}}
+\newcommand*{\HDliteral}[1]{\textcolor{Cyan}{#1}}
+\newcommand*{\HDnumber}[1]{\textcolor{RoyalPurple}{#1}}
+\newcommand*{\HDstring}[1]{\textcolor{BrickRed}{#1}}
+\newcommand*{\HDcomment}[1]{\textit{\textcolor{Mahogany}{#1}}}
+\newcommand*{\HDmeta}[1]{\textcolor{Plum}{#1}}
+\newcommand*{\HDhighlight}{\rlap{\color{lsthighlight}\rule[-.3\baselineskip]{\linewidth}{\baselineskip}}}
+\renewcommand{\theFancyVerbLine}{\tiny\color{gray}\arabic{FancyVerbLine}}
+\makeatletter
+\providecommand*{\captionof}[1]{\def\@captype{#1}\caption}
+\makeatother
+"#;
+
 // https://journals.plos.org/plosntds/article/file?type=supplementary&id=info:doi/10.1371/journal.pntd.0007353.s002&rev=1
 pub const THICKHLINE: &str = r#"
 \newlength\savedwidth
@@ -421,6 +467,11 @@ pub const IMAGE_WITH_TEXT: &str = r#"
 }
 "#;
 
+pub const RENDER_FAILED: &str = r#"
+% Placeholder of a rendered image whose render failed, such that the document still compiles
+\newcommand*{\renderfailed}{\fbox{\textbf{render failed}}}
+"#;
+
 // https://tex.stackexchange.com/q/183699
 pub const SCALE_TIKZ_PICTURE_TO_WIDTH: &str = r#"
 \makeatletter
@@ -4,6 +4,7 @@ use std::path::Path;
 use diagnostic::Spanned;
 
 use super::replace::replace;
+use super::typography::Typographer;
 use crate::backend::latex::InlineEnvironment;
 use crate::backend::{Backend, MediumCodeGenUnit, SimpleCodeGenUnit};
 use crate::config::Config;
@@ -26,7 +27,7 @@ pub struct TextGen;
 
 impl<'a> MediumCodeGenUnit<Cow<'a, str>> for TextGen {
     fn gen<'b, 'c>(
-        text: Spanned<Cow<'a, str>>, _config: &Config, stack: &mut Stack<'b, 'c, impl Backend<'b>, impl Write>,
+        text: Spanned<Cow<'a, str>>, config: &Config, stack: &mut Stack<'b, 'c, impl Backend<'b>, impl Write>,
     ) -> Result<()> {
         let Spanned { value: text, .. } = text;
         // TODO: make code-blocks containing unicode allow inline-math
@@ -36,9 +37,28 @@ impl<'a> MediumCodeGenUnit<Cow<'a, str>> for TextGen {
 
         let in_inline_code = stack.iter().any(|e| e.is_inline_code());
         let in_code_or_math = stack.iter().any(|e| e.is_code() || e.is_math());
-        let in_graphviz = stack.iter().any(|e| e.is_graphviz());
+        let in_rendered_code = stack.iter().any(|e| e.is_rendered_code());
+        let typographer = if config.smart_punctuation && !in_code_or_math && !in_rendered_code {
+            Some(Typographer::new(config.lang))
+        } else {
+            None
+        };
         let mut s = String::with_capacity(text.len() + 20);
-        for c in text.chars() {
+        let mut prev = *stack.prev_char();
+        let mut chars = text.char_indices();
+        while let Some((i, c)) = chars.next() {
+            if let Some(typographer) = &typographer {
+                if let Some((rep, len)) = typographer.replace(&text, i, prev) {
+                    s.push_str(rep);
+                    // all replaced patterns are ascii
+                    for _ in 1..len {
+                        chars.next();
+                    }
+                    prev = text[..i + len].chars().next_back();
+                    continue;
+                }
+            }
+            prev = Some(c);
             match c {
                 '#' if in_inline_code || !in_code_or_math => s.push_str("\\#"),
                 '$' if in_inline_code || !in_code_or_math => s.push_str("\\$"),
@@ -59,13 +79,14 @@ impl<'a> MediumCodeGenUnit<Cow<'a, str>> for TextGen {
                 '✘' => s.push_str("\\text{X}"),
                 '’' => s.push('\''),
                 '"' if in_inline_code => s.push_str("\\\""),
-                c if in_graphviz => s.push(c),
+                c if in_rendered_code => s.push(c),
                 c => match replace(c) {
                     Some(rep) => s.push_str(strfn(rep)),
                     None => s.push(c),
                 },
             }
         }
+        *stack.prev_char() = prev;
         write!(stack.get_out(), "{}", s)?;
         Ok(())
     }
@@ -81,6 +102,35 @@ impl<'a> SimpleCodeGenUnit<Cow<'a, str>> for LatexGen {
     }
 }
 
+#[derive(Debug, Default)]
+pub struct CommentGen;
+
+impl<'a> SimpleCodeGenUnit<Cow<'a, str>> for CommentGen {
+    fn gen(comment: Spanned<Cow<'a, str>>, out: &mut impl Write) -> Result<()> {
+        let mut s = String::with_capacity(comment.value.len() + 20);
+        // pdfcomment doesn't support paragraphs within its argument
+        for (i, word) in comment.value.split_whitespace().enumerate() {
+            if i != 0 {
+                s.push(' ');
+            }
+            for c in word.chars() {
+                match c {
+                    '#' | '$' | '%' | '&' | '_' | '{' | '}' => {
+                        s.push('\\');
+                        s.push(c);
+                    },
+                    '~' => s.push_str("\\textasciitilde{}"),
+                    '^' => s.push_str("\\textasciicircum{}"),
+                    '\\' => s.push_str("\\textbackslash{}"),
+                    c => s.push(c),
+                }
+            }
+        }
+        write!(out, "\\pdfcomment{{{}}}", s)?;
+        Ok(())
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct FootnoteReferenceGen;
 
@@ -153,19 +203,27 @@ pub struct ImageGen;
 impl<'a> SimpleCodeGenUnit<Image<'a>> for ImageGen {
     fn gen(image: Spanned<Image<'a>>, out: &mut impl Write) -> Result<()> {
         let Spanned { value: Image { label, caption, title, alt_text, path, scale, width, height }, .. } = image;
-        includegraphics(out, label, caption, title, alt_text, path, scale, width, height)?;
+        includegraphics(out, label, caption, title, alt_text, path, scale, width, height, false)?;
         Ok(())
     }
 }
 
+/// Includes the image at the given path.
+///
+/// If the image is `rendered` by a job, a placeholder is shown if the job fails.
 fn includegraphics(out: &mut impl Write, label: Option<Spanned<Cow<'_, str>>>,
     caption: Option<Spanned<Cow<'_, str>>>, title: Option<Cow<'_, str>>, alt_text: Option<String>,
     path: impl AsRef<Path>, scale: Option<Spanned<Cow<'_, str>>>, width: Option<Spanned<Cow<'_, str>>>,
-    height: Option<Spanned<Cow<'_, str>>>,
+    height: Option<Spanned<Cow<'_, str>>>, rendered: bool,
 ) -> Result<()> {
     let inline_fig = InlineEnvironment::new_figure(label, caption);
     inline_fig.write_begin(&mut *out)?;
 
+    let path = path.as_ref().to_unix().expect(&format!("non-utf8 path: {:?}", path.as_ref()));
+    if rendered {
+        writeln!(out, "\\IfFileExists{{{}}}{{%", path)?;
+    }
+
     if title.is_some() {
         writeln!(out, "\\pdftooltip{{")?;
     }
@@ -185,8 +243,7 @@ fn includegraphics(out: &mut impl Write, label: Option<Spanned<Cow<'_, str>>>,
         write!(out, "height={},", height)?;
     }
 
-    write!(out, "]{{{}}}", path.as_ref().to_unix()
-        .expect(&format!("non-utf8 path: {:?}", path.as_ref())))?;
+    write!(out, "]{{{}}}", path)?;
 
     if let Some(alt_text) = alt_text {
         write!(out, "{{{}}}", alt_text)?;
@@ -197,6 +254,9 @@ fn includegraphics(out: &mut impl Write, label: Option<Spanned<Cow<'_, str>>>,
     if let Some(title) = title {
         writeln!(out, "}}{{{}}}", title)?;
     }
+    if rendered {
+        writeln!(out, "}}{{\\renderfailed}}")?;
+    }
 
     inline_fig.write_end(out)?;
     Ok(())
@@ -207,18 +267,18 @@ pub struct SvgGen;
 
 impl<'a> MediumCodeGenUnit<Svg<'a>> for SvgGen {
     fn gen<'b, 'c>(svg: Spanned<Svg<'a>>, config: &Config, stack: &mut Stack<'b, 'c, impl Backend<'b>, impl Write>) -> Result<()> {
-        let pdf_path = match svg.value.to_pdf_path(&config.out_dir) {
+        let pdf_path = match svg.value.schedule_pdf_conversion(&config.cache_dir, stack.jobs(), svg.span) {
             Ok(path) => path,
             Err(e) => {
                 stack.diagnostics().error(DiagnosticCode::SvgConversionError)
-                    .with_info_label(svg.span, "can't convert svg to pdf")
+                    .with_info_label(svg.span, "can't read svg to convert it to pdf")
                     .with_note(format!("{}", e))
                     .emit();
                 return Err(Error::Diagnostic);
             },
         };
         let Spanned { value: Svg { label, caption, title, alt_text, path: _, scale, width, height }, .. } = svg;
-        includegraphics(&mut stack.get_out(), label, caption, title, alt_text, pdf_path, scale, width, height)?;
+        includegraphics(&mut stack.get_out(), label, caption, title, alt_text, pdf_path, scale, width, height, true)?;
         Ok(())
     }
 }
@@ -250,11 +310,16 @@ impl SimpleCodeGenUnit<Pdf> for PdfGen {
 #[derive(Debug, Default)]
 pub struct SoftBreakGen;
 
-impl SimpleCodeGenUnit<()> for SoftBreakGen {
-    fn gen(_: Spanned<()>, out: &mut impl Write) -> Result<()> {
+impl MediumCodeGenUnit<()> for SoftBreakGen {
+    fn gen<'b, 'c>(
+        soft_break: Spanned<()>, config: &Config, stack: &mut Stack<'b, 'c, impl Backend<'b>, impl Write>,
+    ) -> Result<()> {
+        if config.hard_breaks {
+            return HardBreakGen::gen(soft_break, config, stack);
+        }
         // soft breaks are only used to split up text in lines in the source file
         // so it's nothing we should translate, but for better readability keep them
-        writeln!(out)?;
+        writeln!(stack.get_out())?;
         Ok(())
     }
 }
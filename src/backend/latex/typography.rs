@@ -0,0 +1,75 @@
+use isolang::Language;
+
+/// Typographic replacements and smart quotes for normal text.
+///
+/// Handles `(c)`, `(r)`, `(tm)`, `--`, `---`, `...` and converts straight quotes into the
+/// quotation marks of the document's language.
+#[derive(Debug, Clone, Copy)]
+pub struct Typographer {
+    double: (&'static str, &'static str),
+    single: (&'static str, &'static str),
+}
+
+impl Typographer {
+    pub fn new(lang: Language) -> Typographer {
+        match lang {
+            Language::Deu | Language::Ces | Language::Slk | Language::Slv | Language::Lit
+            | Language::Est | Language::Bul => Typographer {
+                double: ("\\glqq{}", "\\grqq{}"),
+                single: ("\\glq{}", "\\grq{}"),
+            },
+            Language::Fra | Language::Spa | Language::Ita | Language::Rus | Language::Ukr
+            | Language::Ell | Language::Nor | Language::Nob | Language::Nno => Typographer {
+                double: ("\\guillemotleft{}", "\\guillemotright{}"),
+                single: ("\\guilsinglleft{}", "\\guilsinglright{}"),
+            },
+            _ => Typographer {
+                double: ("``", "''"),
+                single: ("`", "'"),
+            },
+        }
+    }
+
+    /// Returns the replacement for the start of `text[i..]` and the number of bytes it replaces.
+    ///
+    /// `prev` is the character before `i`, if there is one within the current run of inline text.
+    pub fn replace(&self, text: &str, i: usize, prev: Option<char>) -> Option<(&'static str, usize)> {
+        let rest = &text[i..];
+        const PATTERNS: &[(&str, &str)] = &[
+            ("---", "\\textemdash{}"),
+            ("--", "\\textendash{}"),
+            ("...", "\\dots{}"),
+            ("(c)", "\\textcopyright{}"),
+            ("(C)", "\\textcopyright{}"),
+            ("(r)", "\\textregistered{}"),
+            ("(R)", "\\textregistered{}"),
+            ("(tm)", "\\texttrademark{}"),
+            ("(TM)", "\\texttrademark{}"),
+        ];
+        for &(pattern, rep) in PATTERNS {
+            if rest.starts_with(pattern) {
+                return Some((rep, pattern.len()));
+            }
+        }
+
+        let next = rest.chars().nth(1);
+        let (open, close) = match rest.chars().next()? {
+            '"' => self.double,
+            '\'' => {
+                // apostrophe like in `don't`
+                if prev.map_or(false, char::is_alphanumeric) && next.map_or(false, char::is_alphanumeric) {
+                    return Some(("'", 1));
+                }
+                self.single
+            },
+            _ => return None,
+        };
+        let after_opening = prev.map_or(true, |c| c.is_whitespace() || "([{-/".contains(c));
+        let before_content = next.map_or(false, |c| !c.is_whitespace());
+        if after_opening && before_content {
+            Some((open, 1))
+        } else {
+            Some((close, 1))
+        }
+    }
+}
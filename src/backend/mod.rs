@@ -15,12 +15,17 @@ use crate::generator::event::{
     FootnoteDefinition,
     FootnoteReference,
     Graphviz,
+    Gnuplot,
+    Tikz,
     Header,
     Image,
     Svg,
     InterLink,
     Pdf,
     Table,
+    TableCell,
+    TableHead,
+    TableRow,
     TaskListMarker,
     Url,
 };
@@ -44,6 +49,7 @@ pub trait Backend<'a>: Sized + Debug {
     // StatefulCodeGenUnits are used for tags, which have a start and an end and can contain further events.
     type Text: StatefulCodeGenUnit<'a, Self, Cow<'a, str>>;
     type Latex: StatefulCodeGenUnit<'a, Self, Cow<'a, str>>;
+    type Comment: StatefulCodeGenUnit<'a, Self, Cow<'a, str>>;
     type FootnoteReference: StatefulCodeGenUnit<'a, Self, FootnoteReference<'a>>;
     type BiberReferences: StatefulCodeGenUnit<'a, Self, Vec<BiberReference<'a>>>;
     type Url: StatefulCodeGenUnit<'a, Self, Url<'a>>;
@@ -78,9 +84,9 @@ pub trait Backend<'a>: Sized + Debug {
 
     type TableFigure: StatefulCodeGenUnit<'a, Self, Figure<'a>>;
     type Table: StatefulCodeGenUnit<'a, Self, Table<'a>>;
-    type TableHead: StatefulCodeGenUnit<'a, Self, ()>;
-    type TableRow: StatefulCodeGenUnit<'a, Self, ()>;
-    type TableCell: StatefulCodeGenUnit<'a, Self, ()>;
+    type TableHead: StatefulCodeGenUnit<'a, Self, TableHead>;
+    type TableRow: StatefulCodeGenUnit<'a, Self, TableRow>;
+    type TableCell: StatefulCodeGenUnit<'a, Self, TableCell>;
 
     type InlineEmphasis: StatefulCodeGenUnit<'a, Self, ()>;
     type InlineStrong: StatefulCodeGenUnit<'a, Self, ()>;
@@ -91,6 +97,8 @@ pub trait Backend<'a>: Sized + Debug {
     type Equation: StatefulCodeGenUnit<'a, Self, Equation<'a>>;
     type NumberedEquation: StatefulCodeGenUnit<'a, Self, Equation<'a>>;
     type Graphviz: StatefulCodeGenUnit<'a, Self, Graphviz<'a>>;
+    type Gnuplot: StatefulCodeGenUnit<'a, Self, Gnuplot<'a>>;
+    type Tikz: StatefulCodeGenUnit<'a, Self, Tikz<'a>>;
 
     fn new() -> Self;
     fn gen_preamble(&mut self, cfg: &Config, out: &mut impl Write, diagnostics: &'a Diagnostics) -> FatalResult<()>;
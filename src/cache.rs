@@ -0,0 +1,104 @@
+//! Persistent cache of rendered diagrams and converted images.
+//!
+//! Each artifact is stored in its own directory named after the hash of everything it's rendered
+//! from, such that unchanged diagrams are reused across builds.
+//!
+//! Entries are never evicted, as the cache may be shared by multiple documents of a project, such
+//! that a build can't know which entries are still used. Deleting the cache directory is always
+//! safe.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+/// File within the directory of an entry containing the name of the artifact.
+///
+/// It's written after rendering succeeded, such that failed or interrupted renders aren't reused.
+const ARTIFACT: &str = "artifact";
+
+/// Key of a cached artifact, hashing the renderer, its options and the rendered content.
+#[derive(Debug, Clone)]
+pub struct CacheKey(Sha256);
+
+impl CacheKey {
+    pub fn new(renderer: &str) -> CacheKey {
+        CacheKey(Sha256::new()).with(renderer)
+    }
+
+    /// Adds an option or content the artifact depends on.
+    pub fn with(mut self, part: impl AsRef<[u8]>) -> CacheKey {
+        let part = part.as_ref();
+        // length-prefixed, such that `("ab", "c")` and `("a", "bc")` don't collide
+        self.0.update((part.len() as u64).to_le_bytes());
+        self.0.update(part);
+        self
+    }
+
+    /// Directory the artifact of this key is cached in, even if it isn't rendered yet.
+    pub fn dir(&self, cache_dir: &Path) -> PathBuf {
+        cache_dir.join(format!("{:x}", self.0.clone().finalize()))
+    }
+}
+
+/// Returns the path of the cached artifact, rendering it if it isn't cached yet.
+///
+/// The cache directory is usually `Config::cache_dir`.
+///
+/// `render` gets an empty directory to write the artifact and any files it needs to and returns
+/// the path of the artifact within that directory.
+pub fn cached<E: From<io::Error>>(
+    cache_dir: &Path, key: CacheKey, render: impl FnOnce(&Path) -> Result<PathBuf, E>,
+) -> Result<PathBuf, E> {
+    let dir = key.dir(cache_dir);
+    if let Ok(name) = fs::read_to_string(dir.join(ARTIFACT)) {
+        let path = dir.join(name);
+        if path.is_file() {
+            return Ok(path);
+        }
+    }
+
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    let path = match render(&dir) {
+        Ok(path) => path,
+        Err(e) => {
+            let _ = fs::remove_dir_all(&dir);
+            return Err(e);
+        },
+    };
+    let name = path.strip_prefix(&dir).expect("cached artifact must be written into its directory");
+    let name = name.to_str().expect(&format!("non-utf8 path: {:?}", name));
+    fs::write(dir.join(ARTIFACT), name)?;
+    Ok(path)
+}
+
+/// Writes an svg rendered in-process to the cache, such that its conversion to pdf can be reused.
+pub fn write_svg(cache_dir: &Path, name: &str, svg: &str) -> io::Result<PathBuf> {
+    cached(cache_dir, CacheKey::new(name).with(svg), |dir| {
+        let path = dir.join(format!("{}.svg", name));
+        fs::write(&path, svg)?;
+        Ok(path)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::CacheKey;
+
+    fn hex(key: CacheKey) -> String {
+        key.dir(Path::new("")).display().to_string()
+    }
+
+    #[test]
+    fn test_key() {
+        let key = |a: &str, b: &str| hex(CacheKey::new("graphviz").with(a).with(b));
+        assert_eq!(key("dot", "a -> b"), key("dot", "a -> b"));
+        assert_ne!(key("dot", "a -> b"), key("neato", "a -> b"));
+        assert_ne!(key("ab", "c"), key("a", "bc"));
+        assert_ne!(hex(CacheKey::new("svgbob").with("x")), hex(CacheKey::new("sequence").with("x")));
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+/// Configuration of code blocks, `[code]` in the config.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Code {
+    /// Highlighter of code blocks, defaults to `listings`.
+    pub highlighter: Option<Highlighter>,
+    /// Aliases of code block languages, e.g. `rs = "Rust"`, taking precedence over the built-in
+    /// aliases.
+    #[serde(default)]
+    pub languages: BTreeMap<String, String>,
+    /// Commands executing code blocks marked with `exec` by language, e.g. `python = "python3 -B"`.
+    /// The path of the file containing the code is appended as last argument.
+    /// Only available in heradoc.toml, not within the document.
+    #[serde(default)]
+    pub interpreters: BTreeMap<String, String>,
+}
+
+/// Built-in aliases of common language tags to names known by the highlighters.
+const DEFAULT_LANGUAGES: &[(&str, &str)] = &[
+    ("rs", "rust"),
+    ("sh", "bash"),
+    ("shell", "bash"),
+    ("zsh", "bash"),
+    ("console", "bash"),
+    ("py", "python"),
+    ("python3", "python"),
+    ("rb", "ruby"),
+    ("pl", "perl"),
+    ("hs", "haskell"),
+    ("cpp", "c++"),
+    ("cxx", "c++"),
+    ("cc", "c++"),
+    ("hpp", "c++"),
+    ("hh", "c++"),
+    ("h", "c"),
+    ("javascript", "js"),
+    ("ts", "typescript"),
+    ("kt", "kotlin"),
+    ("golang", "go"),
+    ("yml", "yaml"),
+    ("htm", "html"),
+    ("latex", "tex"),
+    ("makefile", "make"),
+    ("m", "matlab"),
+    ("asm", "[x86_64]{Assembler}"),
+    ("asm-x86", "[x86_64]{Assembler}"),
+    ("x86", "[x86_64]{Assembler}"),
+    ("x86-asm", "[x86_64]{Assembler}"),
+];
+
+/// Built-in interpreters of languages, after resolving their aliases.
+const DEFAULT_INTERPRETERS: &[(&str, &str)] = &[
+    ("python", "python3"),
+    ("bash", "bash"),
+    ("ruby", "ruby"),
+    ("perl", "perl"),
+    ("js", "node"),
+    ("r", "Rscript"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase", deny_unknown_fields)]
+pub enum Highlighter {
+    /// The LaTeX package `listings`
+    Listings,
+    /// Built-in highlighting in Rust, emitting coloured LaTeX for the `fancyvrb` package
+    Builtin,
+}
+
+impl Code {
+    /// Merges two code configs, preferring the values of `self`.
+    ///
+    /// Aliases and interpreters are keyed by lowercase language, as code block languages are
+    /// matched case-insensitively.
+    pub fn merge(self, c: Code) -> Code {
+        let lowercase = |map: BTreeMap<String, String>| {
+            map.into_iter().map(|(key, value)| (key.to_lowercase(), value))
+        };
+        let mut languages: BTreeMap<_, _> = lowercase(c.languages).collect();
+        languages.extend(lowercase(self.languages));
+        let mut interpreters: BTreeMap<_, _> = lowercase(c.interpreters).collect();
+        interpreters.extend(lowercase(self.interpreters));
+        Code { highlighter: self.highlighter.or(c.highlighter), languages, interpreters }
+    }
+
+    pub fn highlighter(&self) -> Highlighter {
+        self.highlighter.unwrap_or(Highlighter::Listings)
+    }
+
+    /// Resolves the language of a code block using the configured and built-in aliases.
+    pub fn language<'s>(&'s self, language: &'s str) -> &'s str {
+        if let Some(language) = self.languages.get(&language.to_lowercase()) {
+            return language;
+        }
+        DEFAULT_LANGUAGES
+            .iter()
+            .find(|(alias, _)| alias.eq_ignore_ascii_case(language))
+            .map_or(language, |&(_, language)| language)
+    }
+
+    /// Returns the command and its arguments executing code of the given language.
+    pub fn interpreter(&self, language: &str) -> Option<Vec<&str>> {
+        let configured = |language: &str| {
+            self.interpreters.get(&language.to_lowercase()).map(String::as_str)
+        };
+        let command = configured(language).or_else(|| {
+            let language = self.language(language);
+            configured(language).or_else(|| {
+                DEFAULT_INTERPRETERS
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(language))
+                    .map(|&(_, command)| command)
+            })
+        })?;
+        let command: Vec<_> = command.split_whitespace().collect();
+        if command.is_empty() { None } else { Some(command) }
+    }
+}
@@ -0,0 +1,60 @@
+use std::collections::BTreeMap;
+use std::io;
+
+use serde::Deserialize;
+
+/// Configuration of math, `[math]` in the config.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Math {
+    /// Macros usable in math, e.g. `R = "\\mathbb{R}"` or `norm = ["\\lVert #1 \\rVert", 1]`.
+    #[serde(default)]
+    pub macros: BTreeMap<String, MathMacro>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum MathMacro {
+    /// Macro without arguments
+    Simple(String),
+    /// Macro and its number of arguments
+    WithArgs(String, u8),
+}
+
+impl Math {
+    /// Merges two math configs, preferring the macros of `self`.
+    pub fn merge(self, m: Math) -> Math {
+        let mut macros = m.macros;
+        macros.extend(self.macros);
+        Math { macros }
+    }
+
+    pub fn validate(&self) {
+        for (name, mac) in &self.macros {
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphabetic()) {
+                panic!("Math macro name `{}` must only consist of the letters a-z and A-Z", name);
+            }
+            if let MathMacro::WithArgs(_, args) = mac {
+                if *args > 9 {
+                    panic!("Math macro `{}` has {} arguments, but at most 9 are supported", name, args);
+                }
+            }
+        }
+    }
+
+    /// Writes all macros as LaTeX commands.
+    ///
+    /// Macros are defined with `\newcommand`, such that LaTeX errors if a macro would replace an
+    /// existing command.
+    pub fn write_latex_macros<W: io::Write>(&self, mut out: W) -> io::Result<()> {
+        for (name, mac) in &self.macros {
+            match mac {
+                MathMacro::Simple(body) => writeln!(out, "\\newcommand{{\\{}}}{{{}}}", name, body)?,
+                MathMacro::WithArgs(body, args) => {
+                    writeln!(out, "\\newcommand{{\\{}}}[{}]{{{}}}", name, args, body)?
+                },
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,10 +1,12 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::env;
 use std::fmt;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{self, Read, Write, BufWriter};
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::thread;
 
 use boolinator::Boolinator;
 use isolang::Language;
@@ -15,11 +17,19 @@ use tempdir::TempDir;
 use url::Url;
 use void::Void;
 
+mod code;
 mod geometry;
+mod math;
+mod renderer;
 
+pub use self::code::{Code, Highlighter};
 use self::geometry::Geometry;
+use self::math::Math;
+pub use self::renderer::Renderer;
+use crate::error::{DiagnosticCode, Diagnostics};
 use crate::resolve::remote::Remote;
 use crate::util;
+use crate::CONFIG_SPAN;
 
 // TODO: VecOrSingle to allow `foo = "bar"` instead of `foo = ["bar"]` for single values
 
@@ -38,6 +48,13 @@ pub struct CliArgs {
     /// Config file with additional configuration. Defaults to `Config.toml` if it exists.
     #[structopt(long = "config", long = "cfg", parse(from_os_str))]
     pub configfile: Option<PathBuf>,
+    /// Allow executing code blocks marked with `exec` using the interpreters of the config.
+    /// Only available on the command line, such that documents can't enable it themselves.
+    #[structopt(long)]
+    pub allow_exec: bool,
+    /// Number of diagrams and images rendered in parallel. Defaults to the number of CPUs.
+    #[structopt(short = "j", long)]
+    pub jobs: Option<usize>,
     #[structopt(flatten)]
     pub fileconfig: FileConfig,
 }
@@ -82,6 +99,24 @@ pub struct FileConfig {
     /// If figures should be used by default for images and similar
     #[structopt(long)]
     pub figures: Option<bool>,
+    /// If reviewer notes (`<!-- note: … -->`) should be rendered as PDF annotations
+    #[structopt(long)]
+    pub show_comments: Option<bool>,
+    /// If typographic replacements (`(c)`, `--`, `...`, …) and language-aware smart quotes
+    /// should be applied to text. Defaults to `true`.
+    #[structopt(long)]
+    pub smart_punctuation: Option<bool>,
+    /// If line breaks within paragraphs should be kept as line breaks in the output, as if the
+    /// line ended with two spaces.
+    #[structopt(long)]
+    pub hard_breaks: Option<bool>,
+    /// If rendered diagrams and converted images should be cached across builds.
+    /// Defaults to `true`.
+    #[structopt(long)]
+    pub cache: Option<bool>,
+    /// Directory of the cache, relative to the project root. Defaults to `.heradoc-cache`.
+    #[structopt(long)]
+    pub cache_dir: Option<String>,
 
     /// File template to use. It must contain `HERADOCBODY` on its own line without indentation,
     /// which will be replaced with the rendered body.
@@ -236,6 +271,21 @@ pub struct FileConfig {
     #[structopt(flatten)]
     #[serde(default)]
     pub geometry: Geometry,
+
+    /// Math configuration, only available in config files
+    #[structopt(skip)]
+    #[serde(default)]
+    pub math: Math,
+
+    /// Code block configuration, only available in config files
+    #[structopt(skip)]
+    #[serde(default)]
+    pub code: Code,
+
+    /// Code block languages rendered by external commands, only available in heradoc.toml
+    #[structopt(skip)]
+    #[serde(default)]
+    pub renderers: BTreeMap<String, Renderer>,
 }
 
 #[derive(Debug)]
@@ -254,6 +304,9 @@ pub struct Config {
     pub document_folder: PathBuf,
     pub project_root: PathBuf,
     pub output_type: OutType,
+    pub allow_exec: bool,
+    /// Number of threads scheduled renders run on, see `crate::generator::jobs`.
+    pub jobs: usize,
 
     pub document_type: DocumentType,
 
@@ -261,6 +314,13 @@ pub struct Config {
     pub citestyle: MaybeUnknown<CitationStyle>,
     pub bibstyle: MaybeUnknown<CitationStyle>,
     pub figures: bool,
+    pub show_comments: bool,
+    pub smart_punctuation: bool,
+    pub hard_breaks: bool,
+    /// Directory of rendered diagrams and converted images, see `crate::cache`.
+    ///
+    /// If caching is disabled, this is within the temporary directory.
+    pub cache_dir: PathBuf,
 
     pub template: Option<PathBuf>,
 
@@ -320,11 +380,20 @@ pub struct Config {
 
     // geometry
     pub geometry: Geometry,
+
+    pub math: Math,
+
+    pub code: Code,
+
+    pub renderers: BTreeMap<String, Renderer>,
 }
 
 impl Config {
     /// tempdir must live as long as Config
-    pub fn new(args: CliArgs, mut infile: FileConfig, mut file: FileConfig, mut cfgfile_folder: Option<PathBuf>, tempdir: &TempDir) -> Config {
+    pub fn new(
+        args: CliArgs, mut infile: FileConfig, mut file: FileConfig, mut cfgfile_folder: Option<PathBuf>,
+        tempdir: &TempDir, diagnostics: &Diagnostics,
+    ) -> Config {
         if args.fileconfig.root || args.fileconfig.ignore_toplevel {
             infile = FileConfig::default();
             file = FileConfig::default();
@@ -426,6 +495,26 @@ impl Config {
         header_includes.extend(infile.header_includes);
         header_includes.extend(file.header_includes);
 
+        let math = args.fileconfig.math.merge(infile.math).merge(file.math);
+        math.validate();
+
+        // Commands must only be configured by the user, otherwise opening a document would
+        // execute arbitrary commands.
+        if !infile.code.interpreters.is_empty() {
+            panic!("`[code.interpreters]` can't be set within the document, set it in heradoc.toml instead");
+        }
+        if !infile.renderers.is_empty() {
+            panic!("`[renderers]` can't be set within the document, set it in heradoc.toml instead");
+        }
+        let code = args.fileconfig.code.merge(infile.code).merge(file.code);
+
+        let mut renderers: BTreeMap<_, _> = renderer::default_renderers().collect();
+        renderers.extend(file.renderers);
+        renderers.extend(args.fileconfig.renderers);
+        for (language, renderer) in &renderers {
+            renderer.validate(language);
+        }
+
         let citationstyle =
             args.fileconfig.citationstyle.or(infile.citationstyle).or(file.citationstyle);
 
@@ -449,6 +538,28 @@ impl Config {
             args.fileconfig.abstract2.or(infile.abstract2).or(file.abstract2);
         let abstract2 = resolve_file(&document_folder, &project_root, &tempdir_path, abstract2, "abstract2");
 
+        let cache = args.fileconfig.cache.or(infile.cache).or(file.cache).unwrap_or(true);
+        let cache_dir = match cache {
+            true => {
+                let cache_dir = args.fileconfig.cache_dir.or(infile.cache_dir).or(file.cache_dir);
+                let cache_dir = project_root.join(cache_dir.as_deref().unwrap_or(".heradoc-cache"));
+                // e.g. in a read-only checkout
+                match check_writable(&cache_dir) {
+                    Ok(()) => cache_dir,
+                    Err(e) => {
+                        diagnostics
+                            .warning(DiagnosticCode::ErrorWritingToCache)
+                            .with_error_label(CONFIG_SPAN, format!("can't write to the cache directory {}", cache_dir.display()))
+                            .with_note(format!("cause: {}", e))
+                            .with_note("caching renders in the temporary directory instead")
+                            .emit();
+                        tempdir_path.join("cache")
+                    },
+                }
+            },
+            false => tempdir_path.join("cache"),
+        };
+
         let document_type = args
             .fileconfig
             .document_type
@@ -464,6 +575,10 @@ impl Config {
             document_folder,
             project_root,
             output_type,
+            allow_exec: args.allow_exec,
+            jobs: args.jobs
+                .or_else(|| thread::available_parallelism().ok().map(NonZeroUsize::get))
+                .unwrap_or(1),
             document_type,
             bibliography,
             template,
@@ -488,6 +603,25 @@ impl Config {
                     DocumentType::Thesis | DocumentType::Report => true,
                 },
             ),
+            show_comments: args
+                .fileconfig
+                .show_comments
+                .or(infile.show_comments)
+                .or(file.show_comments)
+                .unwrap_or(false),
+            smart_punctuation: args
+                .fileconfig
+                .smart_punctuation
+                .or(infile.smart_punctuation)
+                .or(file.smart_punctuation)
+                .unwrap_or(true),
+            hard_breaks: args
+                .fileconfig
+                .hard_breaks
+                .or(infile.hard_breaks)
+                .or(file.hard_breaks)
+                .unwrap_or(false),
+            cache_dir,
             fontsize: args
                 .fileconfig
                 .fontsize
@@ -540,10 +674,21 @@ impl Config {
             classoptions,
             header_includes,
             geometry: args.fileconfig.geometry.merge(infile.geometry).merge(file.geometry),
+            math,
+            code,
+            renderers,
         }
     }
 }
 
+/// Creates the directory if it doesn't exist yet and checks that files can be written to it.
+fn check_writable(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let probe = dir.join(".heradoc-write-check");
+    File::create(&probe)?;
+    fs::remove_file(&probe)
+}
+
 /// Tries to resolve given input.
 ///
 /// 1. if it's relative, it's resolved relative to the input file
@@ -0,0 +1,74 @@
+use serde::Deserialize;
+
+/// Code block language rendered by an external command, `[renderers.<language>]` in the config.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Renderer {
+    /// Command rendering the code, e.g. `plantuml -tsvg -pipe`.
+    ///
+    /// By default the code is written to its stdin and the rendered file is read from its stdout.
+    /// `{input}` within an argument is replaced by the path of a file containing the code and
+    /// `{output}` by the path the rendered file must be written to, e.g. `-o{output}`.
+    pub command: RendererCommand,
+    /// File format of the rendered file.
+    pub output: RendererOutput,
+}
+
+/// Built-in renderers of languages, which can be overwritten in the config.
+const DEFAULT_RENDERERS: &[(&str, &str, RendererOutput)] = &[
+    // ABC music notation, one tune per code block
+    ("abc", "abcm2ps -g -q -O - {input}", RendererOutput::Svg),
+];
+
+/// Command of a renderer, either split at whitespace or given as list of arguments.
+///
+/// The list allows arguments containing whitespace, e.g. `["mmdc", "-c", "my config.json"]`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum RendererCommand {
+    Line(String),
+    Args(Vec<String>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase", deny_unknown_fields)]
+pub enum RendererOutput {
+    Svg,
+    Pdf,
+    Png,
+    Jpg,
+}
+
+impl Renderer {
+    /// Returns the command and its arguments.
+    pub fn command(&self) -> Vec<&str> {
+        match &self.command {
+            RendererCommand::Line(command) => command.split_whitespace().collect(),
+            RendererCommand::Args(args) => args.iter().map(String::as_str).collect(),
+        }
+    }
+
+    pub fn validate(&self, language: &str) {
+        if self.command().is_empty() {
+            panic!("Renderer for `{}` has an empty command", language);
+        }
+    }
+}
+
+/// Returns the built-in renderers by language.
+pub fn default_renderers() -> impl Iterator<Item = (String, Renderer)> {
+    DEFAULT_RENDERERS.iter().map(|&(language, command, output)| {
+        (language.to_string(), Renderer { command: RendererCommand::Line(command.to_string()), output })
+    })
+}
+
+impl RendererOutput {
+    pub fn extension(self) -> &'static str {
+        match self {
+            RendererOutput::Svg => "svg",
+            RendererOutput::Pdf => "pdf",
+            RendererOutput::Png => "png",
+            RendererOutput::Jpg => "jpg",
+        }
+    }
+}
@@ -139,6 +139,10 @@ impl<'a> Cskvp<'a> {
         self.label.is_some()
     }
 
+    pub fn has_single(&self, key: &str) -> bool {
+        self.single.iter().any(|Spanned { value, .. }| value == key)
+    }
+
     pub fn take_label(&mut self) -> Option<Spanned<Cow<'a, str>>> {
         self.label.take()
     }
@@ -155,6 +159,10 @@ impl<'a> Cskvp<'a> {
         self.double.remove(key)
     }
 
+    pub fn take_single(&mut self, key: &str) -> Option<Spanned<Cow<'a, str>>> {
+        self.single.remove_element(&key)
+    }
+
     /// Removes all elements from `self`.
     ///
     /// This can be used before dropping `Cskvp` to omit all "unused attribute" warnings.
@@ -97,7 +97,6 @@ pub enum DiagnosticCode {
     MultipleFigureKeys,
     InvalidCskvp,
     InvalidCskv,
-    NotYetImplemented,
     UnapplicableElementConfig,
     MultipleLabels,
     InvalidReference,
@@ -116,6 +115,24 @@ pub enum DiagnosticCode {
     ErrorDownloadingContent,
     UnknownFileFormat,
     MissingFileExtension,
+    InvalidEquationEnvironment,
+    EquationEnvironmentMismatch,
+    InvalidTableSpan,
+    InvalidTableAlignment,
+    ErrorReadingTableFile,
+    InvalidGridTable,
+    InvalidTableStyle,
+    ErrorReadingCodeFile,
+    InvalidCodeSelection,
+    InvalidCodeStyle,
+    UnknownCodeLanguage,
+    CodeExecutionNotAllowed,
+    UnknownInterpreter,
+    InvalidGnuplotTerminal,
+    InvalidDiagram,
+    InvalidChartData,
+    InvalidGraphvizOption,
+    EmptyTableFile,
 
     GraphvizError,
     TempFileError,
@@ -124,6 +141,9 @@ pub enum DiagnosticCode {
     Unsupported,
     SvgConversionError,
     EspeakCreationError,
+    CodeExecutionError,
+    RendererError,
+    GnuplotError,
 
     InternalCompilerError,
 }
@@ -136,7 +156,6 @@ impl ErrorCode for DiagnosticCode {
             DiagnosticCode::MultipleFigureKeys => "0003",
             DiagnosticCode::InvalidCskvp => "0004",
             DiagnosticCode::InvalidCskv => "0005",
-            DiagnosticCode::NotYetImplemented => "0006",
             DiagnosticCode::UnapplicableElementConfig => "0007",
             DiagnosticCode::MultipleLabels => "0008",
             DiagnosticCode::InvalidReference => "0009",
@@ -155,6 +174,24 @@ impl ErrorCode for DiagnosticCode {
             DiagnosticCode::ErrorDownloadingContent => "0022",
             DiagnosticCode::UnknownFileFormat => "0023",
             DiagnosticCode::MissingFileExtension => "0024",
+            DiagnosticCode::InvalidEquationEnvironment => "0025",
+            DiagnosticCode::EquationEnvironmentMismatch => "0026",
+            DiagnosticCode::InvalidTableSpan => "0027",
+            DiagnosticCode::InvalidTableAlignment => "0028",
+            DiagnosticCode::ErrorReadingTableFile => "0029",
+            DiagnosticCode::InvalidGridTable => "0030",
+            DiagnosticCode::InvalidTableStyle => "0031",
+            DiagnosticCode::ErrorReadingCodeFile => "0032",
+            DiagnosticCode::InvalidCodeSelection => "0033",
+            DiagnosticCode::InvalidCodeStyle => "0034",
+            DiagnosticCode::UnknownCodeLanguage => "0035",
+            DiagnosticCode::CodeExecutionNotAllowed => "0036",
+            DiagnosticCode::UnknownInterpreter => "0037",
+            DiagnosticCode::InvalidGnuplotTerminal => "0038",
+            DiagnosticCode::InvalidDiagram => "0039",
+            DiagnosticCode::InvalidChartData => "0040",
+            DiagnosticCode::InvalidGraphvizOption => "0041",
+            DiagnosticCode::EmptyTableFile => "0042",
 
             DiagnosticCode::GraphvizError => "1000",
             DiagnosticCode::TempFileError => "1001",
@@ -163,6 +200,9 @@ impl ErrorCode for DiagnosticCode {
             DiagnosticCode::Unsupported => "1004",
             DiagnosticCode::SvgConversionError => "1005",
             DiagnosticCode::EspeakCreationError => "1006",
+            DiagnosticCode::CodeExecutionError => "1007",
+            DiagnosticCode::RendererError => "1008",
+            DiagnosticCode::GnuplotError => "1009",
 
             DiagnosticCode::InternalCompilerError => "9999",
         }.to_string()
@@ -175,7 +215,6 @@ impl ErrorCode for DiagnosticCode {
             DiagnosticCode::MultipleFigureKeys => "found multiple figure keys".to_string(),
             DiagnosticCode::InvalidCskvp => "invalid comma seperated key-value-pair".to_string(),
             DiagnosticCode::InvalidCskv => "invalid comma seperated value".to_string(),
-            DiagnosticCode::NotYetImplemented => "not yet implemented".to_string(),
             DiagnosticCode::UnapplicableElementConfig => "found element config, but there wasn't an element to apply it to".to_string(),
             DiagnosticCode::MultipleLabels => "multiple labels for the same element".to_string(),
             DiagnosticCode::InvalidReference => "found biber reference, but no bibliography file found".to_string(),
@@ -194,6 +233,24 @@ impl ErrorCode for DiagnosticCode {
             DiagnosticCode::ErrorDownloadingContent => "error downloading content".to_string(),
             DiagnosticCode::UnknownFileFormat => "unknown file format".to_string(),
             DiagnosticCode::MissingFileExtension => "missing file extension".to_string(),
+            DiagnosticCode::InvalidEquationEnvironment => "invalid equation environment".to_string(),
+            DiagnosticCode::EquationEnvironmentMismatch => "equation doesn't match its environment".to_string(),
+            DiagnosticCode::InvalidTableSpan => "invalid merged table cells".to_string(),
+            DiagnosticCode::InvalidTableAlignment => "invalid table alignment".to_string(),
+            DiagnosticCode::ErrorReadingTableFile => "error reading table file".to_string(),
+            DiagnosticCode::InvalidGridTable => "invalid grid table".to_string(),
+            DiagnosticCode::InvalidTableStyle => "invalid table style".to_string(),
+            DiagnosticCode::ErrorReadingCodeFile => "error reading source code file".to_string(),
+            DiagnosticCode::InvalidCodeSelection => "invalid selection of source code lines".to_string(),
+            DiagnosticCode::InvalidCodeStyle => "invalid code block style".to_string(),
+            DiagnosticCode::UnknownCodeLanguage => "unknown code block language".to_string(),
+            DiagnosticCode::CodeExecutionNotAllowed => "executing code blocks isn't allowed".to_string(),
+            DiagnosticCode::UnknownInterpreter => "no interpreter for code block language".to_string(),
+            DiagnosticCode::InvalidGnuplotTerminal => "invalid gnuplot terminal".to_string(),
+            DiagnosticCode::InvalidDiagram => "invalid diagram".to_string(),
+            DiagnosticCode::InvalidChartData => "invalid chart data".to_string(),
+            DiagnosticCode::InvalidGraphvizOption => "invalid graphviz option".to_string(),
+            DiagnosticCode::EmptyTableFile => "empty table file".to_string(),
 
             DiagnosticCode::GraphvizError => "graphviz rendering failed".to_string(),
             DiagnosticCode::TempFileError => "error creating temporary file".to_string(),
@@ -202,6 +259,9 @@ impl ErrorCode for DiagnosticCode {
             DiagnosticCode::Unsupported => "unsupported".to_string(),
             DiagnosticCode::SvgConversionError => "error converting svg".to_string(),
             DiagnosticCode::EspeakCreationError => "error creating espeak file".to_string(),
+            DiagnosticCode::CodeExecutionError => "error executing code block".to_string(),
+            DiagnosticCode::RendererError => "external renderer failed".to_string(),
+            DiagnosticCode::GnuplotError => "gnuplot rendering failed".to_string(),
 
             DiagnosticCode::InternalCompilerError => "internal compiler error".to_string(),
         }
@@ -0,0 +1,368 @@
+//! Bar, line and scatter charts described by a small declarative spec, rendered to SVG.
+//!
+//! ```text
+//! type = line
+//! title = Runtime
+//! x = size
+//! y = quicksort, mergesort
+//! xlabel = Input size
+//! ylabel = Seconds
+//! ---
+//! size,quicksort,mergesort
+//! 1000,0.12,0.15
+//! 2000,0.25,0.31
+//! ```
+//!
+//! The data is CSV with a header row, given inline after `---` or in a file with
+//! `data = results.csv`. `x` selects the column of the x-axis (the first one by default), `y` the
+//! plotted columns (all others by default).
+
+use super::csv;
+use super::svg::{unescape, Anchor, ParseError, SvgBuilder, LINE_HEIGHT};
+
+const PLOT_WIDTH: f64 = 400.0;
+const PLOT_HEIGHT: f64 = 250.0;
+/// Length of the tick marks on the axes
+const TICK: f64 = 5.0;
+/// Approximate number of ticks on numeric axes
+const TICKS: f64 = 5.0;
+/// Part of the width of a category covered by its bars
+const BAR_GROUP: f64 = 0.8;
+/// Size of the color boxes in the legend
+const LEGEND_BOX: f64 = 12.0;
+/// Colors of the plotted columns, repeated for more columns
+const COLORS: &[&str] = &["#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948", "#b07aa1", "#9c755f"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartSpec {
+    kind: ChartKind,
+    title: Option<String>,
+    xlabel: Option<String>,
+    ylabel: Option<String>,
+    x: Option<String>,
+    y: Vec<String>,
+    /// Data file and the 0-based line of the spec it's given in
+    data: Option<(String, usize)>,
+    /// CSV given after `---`
+    inline_data: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChartKind {
+    Bar,
+    Line,
+    Scatter,
+}
+
+impl ChartSpec {
+    pub fn parse(src: &str) -> Result<ChartSpec, ParseError> {
+        let mut kind = None;
+        let mut spec = ChartSpec {
+            kind: ChartKind::Bar,
+            title: None,
+            xlabel: None,
+            ylabel: None,
+            x: None,
+            y: Vec::new(),
+            data: None,
+            inline_data: None,
+        };
+        for (i, line) in src.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line == "---" {
+                if let Some((_, line)) = spec.data {
+                    let message = "data is given both in a file and inline".to_string();
+                    return Err(ParseError { line, message });
+                }
+                spec.inline_data = Some(src.lines().skip(i + 1).collect::<Vec<_>>().join("\n"));
+                break;
+            }
+            let (key, value) = match line.find('=') {
+                Some(idx) => (line[..idx].trim(), line[idx + 1..].trim()),
+                None => return Err(ParseError { line: i, message: "expected `key = value`".to_string() }),
+            };
+            match key {
+                "type" => kind = Some(match value {
+                    "bar" => ChartKind::Bar,
+                    "line" => ChartKind::Line,
+                    "scatter" => ChartKind::Scatter,
+                    _ => {
+                        let message = format!("unknown chart type `{}`, expected `bar`, `line` or `scatter`", value);
+                        return Err(ParseError { line: i, message });
+                    },
+                }),
+                "title" => spec.title = Some(unescape(value)),
+                "xlabel" => spec.xlabel = Some(unescape(value)),
+                "ylabel" => spec.ylabel = Some(unescape(value)),
+                "x" => spec.x = Some(value.to_string()),
+                "y" => {
+                    spec.y = value.split(',').map(str::trim).filter(|c| !c.is_empty()).map(String::from).collect()
+                },
+                "data" => spec.data = Some((value.to_string(), i)),
+                _ => return Err(ParseError { line: i, message: format!("unknown key `{}`", key) }),
+            }
+        }
+        spec.kind = kind.ok_or_else(|| ParseError {
+            line: 0,
+            message: "missing chart type `type = bar`, `type = line` or `type = scatter`".to_string(),
+        })?;
+        if spec.data.is_none() && spec.inline_data.is_none() {
+            let message = "missing data, either `data = file.csv` or CSV after a line `---`".to_string();
+            return Err(ParseError { line: 0, message });
+        }
+        Ok(spec)
+    }
+
+    /// Returns the data file to be resolved and the 0-based line of the spec it's given in.
+    pub fn data_file(&self) -> Option<(&str, usize)> {
+        self.data.as_ref().map(|(file, line)| (file.as_str(), *line))
+    }
+
+    /// Returns the CSV given inline after `---`.
+    pub fn inline_data(&self) -> Option<&str> {
+        self.inline_data.as_deref()
+    }
+
+    /// Renders the chart of the CSV / TSV data, whose first row is the head.
+    pub fn to_svg(&self, data: &str, delimiter: char) -> Result<String, String> {
+        let grid = csv::parse(data, delimiter);
+        let (header, rows) = grid.split_first().ok_or("the data is empty")?;
+        if rows.is_empty() {
+            return Err("the data only contains the header row".to_string());
+        }
+        let column = |name: &str| {
+            header.iter().position(|h| h.trim() == name).ok_or_else(|| {
+                format!("column `{}` doesn't exist, available columns: {}", name, header.join(", "))
+            })
+        };
+        let number = |row: usize, col: usize| {
+            let cell = rows[row].get(col).map_or("", |c| c.trim());
+            cell.parse::<f64>().ok().filter(|n| n.is_finite()).ok_or_else(|| {
+                // row 1 is the header
+                format!("value `{}` in row {} of column `{}` isn't a number", cell, row + 2, header[col].trim())
+            })
+        };
+
+        let x = match &self.x {
+            Some(x) => column(x)?,
+            None => 0,
+        };
+        let y = if self.y.is_empty() {
+            (0..header.len()).filter(|&col| col != x).collect()
+        } else {
+            self.y.iter().map(|name| column(name)).collect::<Result<Vec<_>, _>>()?
+        };
+        if y.is_empty() {
+            return Err("there are no columns to plot besides the x-axis".to_string());
+        }
+        let series = y.iter()
+            .map(|&col| (0..rows.len()).map(|row| number(row, col)).collect::<Result<Vec<_>, _>>())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut values = series.iter().flatten().copied();
+        let first = values.next().unwrap();
+        let (mut min, mut max) = values.fold((first, first), |(min, max), v| (min.min(v), max.max(v)));
+        if self.kind == ChartKind::Bar {
+            // bars start at 0
+            min = min.min(0.0);
+            max = max.max(0.0);
+        }
+        let y_axis = Axis::new(min, max);
+        let y_pos = |value: f64| PLOT_HEIGHT - y_axis.position(value, PLOT_HEIGHT);
+
+        let mut svg = SvgBuilder::new();
+        for tick in y_axis.ticks() {
+            let y = y_pos(tick);
+            svg.colored_polyline(&[(0.0, y), (PLOT_WIDTH, y)], "#dddddd", "");
+            svg.polyline(&[(-TICK, y), (0.0, y)], "");
+            svg.text(-TICK - 3.0, y - LINE_HEIGHT / 2.0, &y_axis.format(tick), Anchor::End);
+        }
+
+        match self.kind {
+            ChartKind::Bar => {
+                let group = PLOT_WIDTH / rows.len() as f64;
+                let bar = group * BAR_GROUP / series.len() as f64;
+                let zero = y_pos(0.0);
+                for (row, cells) in rows.iter().enumerate() {
+                    let left = row as f64 * group + group * (1.0 - BAR_GROUP) / 2.0;
+                    for (i, values) in series.iter().enumerate() {
+                        let y = y_pos(values[row]);
+                        svg.rect(left + i as f64 * bar, y.min(zero), bar, (y - zero).abs(), color(i));
+                    }
+                    let center = (row as f64 + 0.5) * group;
+                    let category = cells.get(x).map_or("", |c| c.trim());
+                    svg.polyline(&[(center, PLOT_HEIGHT), (center, PLOT_HEIGHT + TICK)], "");
+                    svg.text(center, PLOT_HEIGHT + TICK, category, Anchor::Middle);
+                }
+            },
+            ChartKind::Line | ChartKind::Scatter => {
+                let xs = (0..rows.len()).map(|row| number(row, x)).collect::<Result<Vec<_>, _>>()?;
+                let (min, max) = xs.iter().fold((xs[0], xs[0]), |(min, max), &v| (min.min(v), max.max(v)));
+                let x_axis = Axis::new(min, max);
+                let x_pos = |value: f64| x_axis.position(value, PLOT_WIDTH);
+                for tick in x_axis.ticks() {
+                    let x = x_pos(tick);
+                    svg.polyline(&[(x, PLOT_HEIGHT), (x, PLOT_HEIGHT + TICK)], "");
+                    svg.text(x, PLOT_HEIGHT + TICK, &x_axis.format(tick), Anchor::Middle);
+                }
+                for (i, values) in series.iter().enumerate() {
+                    let points: Vec<_> = xs.iter().zip(values).map(|(&x, &y)| (x_pos(x), y_pos(y))).collect();
+                    if self.kind == ChartKind::Line {
+                        svg.colored_polyline(&points, color(i), r#"stroke-width="2""#);
+                    }
+                    let radius = if self.kind == ChartKind::Line { 2.5 } else { 4.0 };
+                    for &(x, y) in &points {
+                        svg.circle(x, y, radius, color(i));
+                    }
+                }
+            },
+        }
+        svg.polyline(&[(0.0, 0.0), (0.0, PLOT_HEIGHT), (PLOT_WIDTH, PLOT_HEIGHT)], "");
+
+        if let Some(xlabel) = &self.xlabel {
+            svg.text(PLOT_WIDTH / 2.0, PLOT_HEIGHT + TICK + LINE_HEIGHT + 6.0, xlabel, Anchor::Middle);
+        }
+        // the y label is written above the axis, the title above it
+        let mut top = -LINE_HEIGHT * 1.5;
+        if let Some(ylabel) = &self.ylabel {
+            svg.text(0.0, top, ylabel, Anchor::Middle);
+            top -= LINE_HEIGHT;
+        }
+        if let Some(title) = &self.title {
+            svg.text(PLOT_WIDTH / 2.0, top - LINE_HEIGHT * (title.lines().count().max(1) - 1) as f64, title, Anchor::Middle);
+        }
+        if series.len() > 1 {
+            let left = PLOT_WIDTH + 20.0;
+            for (i, &col) in y.iter().enumerate() {
+                let top = i as f64 * LINE_HEIGHT;
+                svg.rect(left, top + (LINE_HEIGHT - LEGEND_BOX) / 2.0, LEGEND_BOX, LEGEND_BOX, color(i));
+                svg.text(left + LEGEND_BOX + 6.0, top, header[col].trim(), Anchor::Start);
+            }
+        }
+        Ok(svg.finish())
+    }
+}
+
+fn color(series: usize) -> &'static str {
+    COLORS[series % COLORS.len()]
+}
+
+/// Numeric axis covering a range of values with ticks at round numbers.
+#[derive(Debug, PartialEq)]
+struct Axis {
+    min: f64,
+    max: f64,
+    step: f64,
+}
+
+impl Axis {
+    fn new(min: f64, max: f64) -> Axis {
+        let (min, max) = if min == max { (min - 1.0, max + 1.0) } else { (min, max) };
+        let step = nice_step((max - min) / TICKS);
+        Axis { min: (min / step).floor() * step, max: (max / step).ceil() * step, step }
+    }
+
+    /// Returns the position of the value on an axis of the given length.
+    fn position(&self, value: f64, length: f64) -> f64 {
+        (value - self.min) / (self.max - self.min) * length
+    }
+
+    fn ticks(&self) -> Vec<f64> {
+        let count = ((self.max - self.min) / self.step).round() as usize;
+        (0..=count).map(|i| self.min + i as f64 * self.step).collect()
+    }
+
+    /// Formats a tick with as many decimals as the step needs.
+    fn format(&self, value: f64) -> String {
+        let decimals = (-self.step.log10().floor()).max(0.0) as usize;
+        // avoid `-0`
+        let value = if value.abs() < self.step / 2.0 { 0.0 } else { value };
+        format!("{:.*}", decimals, value)
+    }
+}
+
+/// Rounds the step up to 1, 2 or 5 times a power of 10.
+fn nice_step(step: f64) -> f64 {
+    let magnitude = 10f64.powf(step.log10().floor());
+    let nice = match step / magnitude {
+        f if f <= 1.0 => 1.0,
+        f if f <= 2.0 => 2.0,
+        f if f <= 5.0 => 5.0,
+        _ => 10.0,
+    };
+    nice * magnitude
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SRC: &str = "type = line
+title = Runtime
+y = quicksort, mergesort
+xlabel = Input size
+---
+size,quicksort,mergesort
+1000,0.12,0.15
+2000,0.25,0.31";
+
+    #[test]
+    fn test_parse() {
+        let spec = ChartSpec::parse(SRC).unwrap();
+        assert_eq!(spec.kind, ChartKind::Line);
+        assert_eq!(spec.title.as_deref(), Some("Runtime"));
+        assert_eq!(spec.y, vec!["quicksort".to_string(), "mergesort".to_string()]);
+        assert_eq!(spec.data_file(), None);
+        assert_eq!(spec.inline_data(), Some("size,quicksort,mergesort\n1000,0.12,0.15\n2000,0.25,0.31"));
+
+        let spec = ChartSpec::parse("type = bar\n\ndata = results.csv").unwrap();
+        assert_eq!(spec.data_file(), Some(("results.csv", 2)));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(ChartSpec::parse("type = pie\n---\na,b").unwrap_err().line, 0);
+        assert_eq!(ChartSpec::parse("type = bar\ncolor: red\n---\na,b").unwrap_err().line, 1);
+        assert_eq!(ChartSpec::parse("type = bar\nfoo = bar\n---\na,b").unwrap_err().line, 1);
+        assert_eq!(ChartSpec::parse("data = a.csv\n---\na,b").unwrap_err().line, 0);
+        assert_eq!(ChartSpec::parse("type = bar\ndata = a.csv\n---\na,b").unwrap_err().line, 1);
+        assert_eq!(ChartSpec::parse("type = bar").unwrap_err().line, 0);
+    }
+
+    #[test]
+    fn test_to_svg() {
+        let spec = ChartSpec::parse(SRC).unwrap();
+        let svg = spec.to_svg(spec.inline_data().unwrap(), ',').unwrap();
+        assert_eq!(svg.matches("<circle").count(), 4);
+        assert!(svg.contains(">Runtime</text>"));
+        assert!(svg.contains(">mergesort</text>"));
+
+        let spec = ChartSpec::parse("type = bar\ndata = a.tsv").unwrap();
+        let svg = spec.to_svg("lang\tloc\nRust\t120\nC\t-30\n", '\t').unwrap();
+        assert_eq!(svg.matches("<rect").count(), 2);
+        assert!(svg.contains(">Rust</text>"));
+    }
+
+    #[test]
+    fn test_data_errors() {
+        let spec = ChartSpec::parse("type = scatter\ny = c\ndata = a.csv").unwrap();
+        assert!(spec.to_svg("", ',').is_err());
+        assert!(spec.to_svg("a,b", ',').is_err());
+        assert_eq!(spec.to_svg("a,b\n1,2", ',').unwrap_err(), "column `c` doesn't exist, available columns: a, b");
+        assert_eq!(spec.to_svg("a,c\n1,x", ',').unwrap_err(), "value `x` in row 2 of column `c` isn't a number");
+    }
+
+    #[test]
+    fn test_axis() {
+        assert_eq!(nice_step(0.13), 0.2);
+        assert_eq!(nice_step(30.0), 50.0);
+        assert_eq!(nice_step(100.0), 100.0);
+        let axis = Axis::new(-30.0, 120.0);
+        assert_eq!(axis, Axis { min: -50.0, max: 150.0, step: 50.0 });
+        assert_eq!(axis.ticks(), vec![-50.0, 0.0, 50.0, 100.0, 150.0]);
+        assert_eq!(Axis::new(0.12, 0.31).format(0.2), "0.20");
+    }
+}
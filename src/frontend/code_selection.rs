@@ -0,0 +1,138 @@
+/// Part of a source file to include as code block.
+#[derive(Debug, PartialEq, Eq)]
+pub enum CodeSelection<'s> {
+    /// The whole file without the lines containing region markers
+    All,
+    /// Lines `start..=end` starting at 1, until the end of the file if `end` is `None`
+    Lines { start: usize, end: Option<usize> },
+    /// Lines between the marker comments `ANCHOR: name` and `ANCHOR_END: name`
+    Region(&'s str),
+}
+
+impl<'s> CodeSelection<'s> {
+    /// Parses the fragment of an include url: `#L10-L42`, `#L10-42`, `#L10` or `#region-name`.
+    pub fn from_fragment(fragment: &'s str) -> Result<CodeSelection<'s>, String> {
+        let lines = fragment.strip_prefix('L').filter(|lines| lines.starts_with(|c: char| c.is_ascii_digit()));
+        match lines {
+            Some(lines) => CodeSelection::from_lines(&lines.replace("-L", "-")),
+            None if fragment.is_empty() => Ok(CodeSelection::All),
+            None => Ok(CodeSelection::Region(fragment)),
+        }
+    }
+
+    /// Parses a line range like `10-42`, `10-` or `10`.
+    pub fn from_lines(lines: &str) -> Result<CodeSelection<'s>, String> {
+        let parse = |line: &str| match line.trim().parse::<usize>() {
+            Ok(0) => Err("line numbers start at 1".to_string()),
+            Ok(line) => Ok(line),
+            Err(e) => Err(format!("invalid line number `{}`: {}", line.trim(), e)),
+        };
+        let (start, end) = match lines.find('-') {
+            Some(idx) if lines[idx + 1..].trim().is_empty() => (parse(&lines[..idx])?, None),
+            Some(idx) => (parse(&lines[..idx])?, Some(parse(&lines[idx + 1..])?)),
+            None => {
+                let line = parse(lines)?;
+                (line, Some(line))
+            },
+        };
+        match end {
+            Some(end) if end < start => Err(format!("the range {}-{} ends before it starts", start, end)),
+            _ => Ok(CodeSelection::Lines { start, end }),
+        }
+    }
+}
+
+/// Returns the selected part of the source code, optionally removing the common indentation.
+///
+/// Lines containing region markers like `// ANCHOR: name` are removed when the whole file or a
+/// region is selected. Explicitly selected lines are kept as they are, such that the printed line
+/// numbers match the file.
+pub fn select_code(content: &str, selection: &CodeSelection<'_>, dedent: bool) -> Result<String, String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let selected: Vec<&str> = match *selection {
+        CodeSelection::All => lines.into_iter().filter(|line| !is_marker(line)).collect(),
+        CodeSelection::Lines { start, end } => {
+            let end = end.unwrap_or(lines.len());
+            if start > lines.len() || end > lines.len() {
+                let line = start.max(end);
+                return Err(format!("line {} selected, but the file only has {} lines", line, lines.len()));
+            }
+            lines[start - 1..end].to_vec()
+        },
+        CodeSelection::Region(name) => {
+            let start = lines
+                .iter()
+                .position(|line| marker(line, "ANCHOR:") == Some(name))
+                .ok_or_else(|| format!("region `{}` not found, start it with `ANCHOR: {}`", name, name))?;
+            let len = lines[start + 1..]
+                .iter()
+                .position(|line| marker(line, "ANCHOR_END:") == Some(name))
+                .ok_or_else(|| format!("region `{}` isn't closed with `ANCHOR_END: {}`", name, name))?;
+            lines[start + 1..start + 1 + len].iter().cloned().filter(|line| !is_marker(line)).collect()
+        },
+    };
+
+    let indent = if dedent {
+        selected
+            .iter()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.len() - line.trim_start().len())
+            .min()
+            .unwrap_or(0)
+    } else {
+        0
+    };
+    let mut code = String::new();
+    for line in selected {
+        code.push_str(line.get(indent..).unwrap_or("").trim_end());
+        code.push('\n');
+    }
+    Ok(code)
+}
+
+/// Returns the region name if the line contains the given marker like `// ANCHOR: name`.
+fn marker<'l>(line: &'l str, marker: &str) -> Option<&'l str> {
+    let idx = line.find(marker)?;
+    line[idx + marker.len()..].split_whitespace().next()
+}
+
+fn is_marker(line: &str) -> bool {
+    marker(line, "ANCHOR:").is_some() || marker(line, "ANCHOR_END:").is_some()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{select_code, CodeSelection};
+
+    const CODE: &str = "\
+fn main() {
+    // ANCHOR: body
+    let x = 1;
+    // ANCHOR: print
+    println!(\"{}\", x);
+    // ANCHOR_END: print
+    // ANCHOR_END: body
+}
+";
+
+    #[test]
+    fn test_selection() {
+        assert_eq!(CodeSelection::from_fragment("L3-L5"), Ok(CodeSelection::Lines { start: 3, end: Some(5) }));
+        assert_eq!(CodeSelection::from_fragment("L3"), Ok(CodeSelection::Lines { start: 3, end: Some(3) }));
+        assert_eq!(CodeSelection::from_fragment("Lexer"), Ok(CodeSelection::Region("Lexer")));
+        assert_eq!(CodeSelection::from_lines("3-"), Ok(CodeSelection::Lines { start: 3, end: None }));
+        assert!(CodeSelection::from_lines("5-3").is_err());
+        assert!(CodeSelection::from_lines("0-3").is_err());
+        assert!(CodeSelection::from_lines("a").is_err());
+    }
+
+    #[test]
+    fn test_select_code() {
+        let lines = CodeSelection::Lines { start: 3, end: Some(5) };
+        assert_eq!(select_code(CODE, &lines, false).unwrap(), "    let x = 1;\n    // ANCHOR: print\n    println!(\"{}\", x);\n");
+        assert_eq!(select_code(CODE, &CodeSelection::Region("body"), true).unwrap(), "let x = 1;\nprintln!(\"{}\", x);\n");
+        assert_eq!(select_code(CODE, &CodeSelection::All, true).unwrap(), "fn main() {\n    let x = 1;\n    println!(\"{}\", x);\n}\n");
+        assert!(select_code(CODE, &CodeSelection::Region("foo"), true).is_err());
+        assert!(select_code(CODE, &CodeSelection::Lines { start: 5, end: Some(20) }, true).is_err());
+    }
+}
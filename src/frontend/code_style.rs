@@ -0,0 +1,93 @@
+use diagnostic::Spanned;
+
+use super::event::CodeStyle;
+use crate::cskvp::Cskvp;
+use crate::error::DiagnosticCode;
+use crate::Diagnostics;
+
+/// Takes the style options of a code block from its element config.
+///
+/// * `linenos` / `nolinenos`: show or hide line numbers
+/// * `firstline=120`: number of the first line
+/// * `highlight="3,5-7"`: lines with a highlighted background, using the printed line numbers
+/// * `mathescape`: `$…$` within the code is rendered as LaTeX math
+pub fn take_style(cskvp: &mut Cskvp<'_>, diagnostics: &Diagnostics) -> CodeStyle {
+    let linenos = match (cskvp.take_single("linenos"), cskvp.take_single("nolinenos")) {
+        (Some(_), Some(Spanned { span, .. })) => {
+            diagnostics
+                .warning(DiagnosticCode::InvalidCodeStyle)
+                .with_error_label(span, "both `linenos` and `nolinenos` given")
+                .with_note("showing line numbers")
+                .emit();
+            Some(true)
+        },
+        (Some(_), None) => Some(true),
+        (None, Some(_)) => Some(false),
+        (None, None) => None,
+    };
+    let firstline = cskvp.take_double("firstline").and_then(|Spanned { value, span }| {
+        match value.trim().parse() {
+            Ok(firstline) => Some(firstline),
+            Err(e) => {
+                diagnostics
+                    .warning(DiagnosticCode::InvalidCodeStyle)
+                    .with_error_label(span, format!("invalid first line `{}`", value))
+                    .with_note(format!("cause: {}", e))
+                    .emit();
+                None
+            },
+        }
+    });
+    let highlight = match cskvp.take_double("highlight") {
+        None => Vec::new(),
+        Some(Spanned { value, span }) => parse_ranges(&value).unwrap_or_else(|e| {
+            diagnostics
+                .warning(DiagnosticCode::InvalidCodeStyle)
+                .with_error_label(span, "invalid highlighted lines")
+                .with_note(e)
+                .with_note("lines must be given like `highlight=\"3,5-7\"`")
+                .emit();
+            Vec::new()
+        }),
+    };
+    CodeStyle {
+        linenos,
+        firstline,
+        highlight,
+        mathescape: cskvp.take_single("mathescape").is_some(),
+    }
+}
+
+/// Parses line ranges like `3,5-7` or `3 5-7` into inclusive ranges.
+fn parse_ranges(ranges: &str) -> Result<Vec<(usize, usize)>, String> {
+    let parse = |line: &str| {
+        line.trim().parse::<usize>().map_err(|e| format!("invalid line number `{}`: {}", line.trim(), e))
+    };
+    ranges
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|range| !range.is_empty())
+        .map(|range| {
+            let (start, end) = match range.find('-') {
+                Some(idx) => (parse(&range[..idx])?, parse(&range[idx + 1..])?),
+                None => (parse(range)?, parse(range)?),
+            };
+            if end < start {
+                return Err(format!("the range {}-{} ends before it starts", start, end));
+            }
+            Ok((start, end))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_ranges;
+
+    #[test]
+    fn test_parse_ranges() {
+        assert_eq!(parse_ranges("3,5-7"), Ok(vec![(3, 3), (5, 7)]));
+        assert_eq!(parse_ranges("3 5-7, 10"), Ok(vec![(3, 3), (5, 7), (10, 10)]));
+        assert!(parse_ranges("7-5").is_err());
+        assert!(parse_ranges("a").is_err());
+    }
+}
@@ -4,12 +4,13 @@ use diagnostic::Spanned;
 
 use str_concat;
 
-use super::convert_cow::{ConvertCow, Event};
+use super::convert_cow::Event;
+use super::grid_table::GridTables;
 
-pub struct Concat<'a>(Peekable<ConvertCow<'a>>);
+pub struct Concat<'a>(Peekable<GridTables<'a>>);
 
 impl<'a> Concat<'a> {
-    pub fn new(i: ConvertCow<'a>) -> Self {
+    pub fn new(i: GridTables<'a>) -> Self {
         Concat(i.peekable())
     }
 }
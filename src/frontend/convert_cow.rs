@@ -39,6 +39,12 @@ pub enum Event<'a> {
     Rule,
     /// A task list marker, rendered as a checkbox in HTML. Contains a true when it is checked
     TaskListMarker(bool),
+    /// `$inline math$`, not emitted by pulldown-cmark but by `DollarMath`
+    InlineMath(Cow<'a, str>),
+    /// `$$display math$$`, not emitted by pulldown-cmark but by `DollarMath`
+    DisplayMath(Cow<'a, str>),
+    /// Raw source of a grid table, not emitted by pulldown-cmark but by `GridTables`
+    GridTable(Cow<'a, str>),
 }
 
 impl<'a> From<CmarkEvent<'a>> for Event<'a> {
@@ -0,0 +1,185 @@
+use std::borrow::Cow;
+use diagnostic::{Span, Spanned};
+
+use pulldown_cmark::Alignment;
+
+use super::event::{Event, Table, TableHead, TableStyle, Tag};
+use super::table_spans::{self, TableLayout};
+use super::table_style;
+use crate::error::DiagnosticCode;
+use crate::Diagnostics;
+
+/// Options of a CSV / TSV include given in its element config.
+#[derive(Debug)]
+pub struct CsvTable<'a> {
+    pub label: Option<Spanned<Cow<'a, str>>>,
+    pub caption: Option<Spanned<Cow<'a, str>>>,
+    /// If the first row is the table head
+    pub header: bool,
+    /// Alignment of each column, e.g. `lcr`
+    pub align: Option<Spanned<Cow<'a, str>>>,
+    pub style: TableStyle<'a>,
+}
+
+/// Converts a CSV / TSV file into the same table events the frontend emits for markdown tables.
+///
+/// Like in markdown tables, cells can be merged with `<<` and `^^`.
+///
+/// Returns `None` if the file doesn't contain any cells, after emitting a diagnostic.
+pub fn csv_table_events<'a>(
+    content: &str, delimiter: char, table: CsvTable<'a>, span: Span, diagnostics: &Diagnostics,
+) -> Option<Vec<Spanned<Event<'a>>>> {
+    let CsvTable { label, caption, header, align, style } = table;
+    let mut grid = parse(content, delimiter);
+    // rows with fewer cells are filled up with empty ones
+    let columns = grid.iter().map(Vec::len).max().unwrap_or(0);
+    if columns == 0 {
+        diagnostics
+            .error(DiagnosticCode::EmptyTableFile)
+            .with_error_label(span, "this table file doesn't contain any cells")
+            .with_note("skipping over it")
+            .emit();
+        return None;
+    }
+    for row in &mut grid {
+        row.resize(columns, String::new());
+    }
+    let alignment = match align {
+        Some(align) => alignment(align, columns, diagnostics),
+        None => vec![Alignment::None; columns],
+    };
+
+    let head_rows = if header && !grid.is_empty() { 1 } else { 0 };
+    let widths = table_style::widths(style.widths.as_ref(), columns, diagnostics);
+    let TableLayout { columns, cells, rows, invalid } =
+        table_spans::layout_table(&grid, alignment, head_rows, widths, style.booktabs);
+    if !invalid.is_empty() {
+        diagnostics
+            .warning(DiagnosticCode::InvalidTableSpan)
+            .with_error_label(span, "this table contains merged cells not forming a rectangle")
+            .with_note(format!(
+                "affected cells (row, column): {}",
+                invalid.iter().map(|(r, c)| format!("({}, {})", r + 1, c + 1)).collect::<Vec<_>>().join(", "),
+            ))
+            .with_note("ignoring the merge markers of those cells")
+            .emit();
+    }
+
+    let mut events = Vec::new();
+    let head = TableHead { booktabs: style.booktabs, longtable: style.longtable };
+    let tag = Tag::Table(Table { label, caption, columns, style });
+    events.push(Spanned::new(Event::Start(tag.clone()), span));
+    let iter = grid.into_iter().zip(cells).zip(rows).enumerate();
+    for (r, ((texts, cells), row)) in iter {
+        let row_tag = if r < head_rows { Tag::TableHead(head.clone()) } else { Tag::TableRow(row) };
+        events.push(Spanned::new(Event::Start(row_tag.clone()), span));
+        for (text, cell) in texts.into_iter().zip(cells) {
+            let cell = match cell {
+                Some(cell) => cell,
+                None => continue,
+            };
+            let covered = cell.covered;
+            events.push(Spanned::new(Event::Start(Tag::TableCell(cell.clone())), span));
+            if !covered && !text.is_empty() {
+                events.push(Spanned::new(Event::Text(Cow::Owned(text)), span));
+            }
+            events.push(Spanned::new(Event::End(Tag::TableCell(cell)), span));
+        }
+        events.push(Spanned::new(Event::End(row_tag), span));
+    }
+    events.push(Spanned::new(Event::End(tag), span));
+    Some(events)
+}
+
+/// Parses the alignment of columns given as one letter per column: `l`eft, `c`enter, `r`ight
+/// or `-` for the default alignment.
+fn alignment(align: Spanned<Cow<'_, str>>, columns: usize, diagnostics: &Diagnostics) -> Vec<Alignment> {
+    let mut alignment: Vec<_> = align.value.chars().map(|c| match c {
+        'l' | 'L' => Alignment::Left,
+        'c' | 'C' => Alignment::Center,
+        'r' | 'R' => Alignment::Right,
+        '-' => Alignment::None,
+        c => {
+            diagnostics
+                .warning(DiagnosticCode::InvalidTableAlignment)
+                .with_error_label(align.span, format!("unknown alignment `{}`", c))
+                .with_note("use `l`, `c`, `r` or `-` for each column")
+                .emit();
+            Alignment::None
+        },
+    }).collect();
+    if alignment.len() != columns {
+        diagnostics
+            .warning(DiagnosticCode::InvalidTableAlignment)
+            .with_error_label(align.span, format!("alignment of {} columns specified", alignment.len()))
+            .with_note(format!("the table has {} columns", columns))
+            .emit();
+    }
+    alignment.resize(columns, Alignment::None);
+    alignment
+}
+
+/// Parses CSV as described in RFC 4180 with the given delimiter.
+///
+/// Fields can be quoted with `"`, which allows them to contain the delimiter, newlines and
+/// quotes escaped as `""`. Empty lines are skipped.
+pub(super) fn parse(content: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    // if the current field was quoted, so that an empty row isn't skipped
+    let mut was_quoted = false;
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            },
+            '"' if field.is_empty() => {
+                quoted = true;
+                was_quoted = true;
+            },
+            c if quoted => field.push(c),
+            c if c == delimiter => row.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => (),
+            '\n' => {
+                if !row.is_empty() || !field.is_empty() || was_quoted {
+                    row.push(std::mem::take(&mut field));
+                    rows.push(std::mem::take(&mut row));
+                }
+                was_quoted = false;
+            },
+            c => field.push(c),
+        }
+    }
+    if !row.is_empty() || !field.is_empty() || was_quoted {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("a,b,c\n1,2,3\n", ','), vec![vec!["a", "b", "c"], vec!["1", "2", "3"]]);
+        assert_eq!(parse("a,,\r\n\n1", ','), vec![vec!["a", "", ""], vec!["1"]]);
+        assert_eq!(parse("a\tb,c\n", '\t'), vec![vec!["a", "b,c"]]);
+    }
+
+    #[test]
+    fn test_quoted() {
+        assert_eq!(parse("\"a,b\",\"say \"\"hi\"\"\"\n", ','), vec![vec!["a,b", "say \"hi\""]]);
+        assert_eq!(parse("\"multi\nline\",x", ','), vec![vec!["multi\nline", "x"]]);
+        assert_eq!(parse("\"\"", ','), vec![vec![""]]);
+    }
+}
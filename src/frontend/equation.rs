@@ -0,0 +1,139 @@
+use std::borrow::Cow;
+use diagnostic::{Span, Spanned};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::event::{EquationEnvironment, Event};
+use crate::error::DiagnosticCode;
+use crate::Diagnostics;
+
+/// Row of an equation with the number of top-level `&` it contains.
+struct Row {
+    span: Span,
+    ampersands: usize,
+}
+
+/// Splits the content of an equation into text and label events, validating it against the
+/// environment.
+///
+/// Rows can be labelled with `{#label}` at their end, e.g. `a &= b {#eq-first} \\`.
+pub fn convert_equation<'a>(
+    Spanned { value: content, span }: Spanned<Cow<'a, str>>, env: EquationEnvironment, numbered: bool,
+    diagnostics: &Diagnostics,
+) -> Vec<Spanned<Event<'a>>> {
+    lazy_static! {
+        // Matches a trailing `{#my-label}` of a row, optionally followed by `\\`
+        static ref RE: Regex = Regex::new(r"\{#([a-zA-Z0-9-_]+)\}\s*(\\\\)?\s*$").unwrap();
+    }
+
+    let rows = rows(&content, span);
+    validate(&rows, env, span, diagnostics);
+
+    let mut events = Vec::new();
+    let mut start = 0;
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        let captures = match RE.captures(line) {
+            Some(captures) => captures,
+            None => continue,
+        };
+        let group0 = captures.get(0).unwrap();
+        let label = captures.get(1).unwrap();
+        let label_span = Span { start: span.start + line_start + group0.start(), end: span.start + line_start + label.end() + 1, ..span };
+        if !numbered || !env.numbers_rows() {
+            diagnostics
+                .warning(DiagnosticCode::EquationEnvironmentMismatch)
+                .with_error_label(label_span, "row label defined here")
+                .with_note(if numbered {
+                    format!("`{}` has only a single number, which can be labelled in the element config", env)
+                } else {
+                    "rows of unnumbered equations can't be referenced".to_string()
+                })
+                .with_note("ignoring this label")
+                .emit();
+        }
+
+        let label_start = line_start + group0.start();
+        events.push(Spanned::new(Event::Text(slice(&content, start, label_start)), span));
+        if numbered && env.numbers_rows() {
+            let label = slice(&content, line_start + label.start(), line_start + label.end());
+            events.push(Spanned::new(Event::Label(label), label_span));
+        }
+        // keep `\\` and the newline
+        start = line_start + captures.get(2).map_or(group0.end(), |m| m.start());
+    }
+    events.push(Spanned::new(Event::Text(slice(&content, start, content.len())), span));
+    events
+}
+
+fn slice<'a>(content: &Cow<'a, str>, start: usize, end: usize) -> Cow<'a, str> {
+    match content {
+        Cow::Borrowed(s) => Cow::Borrowed(&s[start..end]),
+        Cow::Owned(s) => Cow::Owned(s[start..end].to_string()),
+    }
+}
+
+/// Splits the content at top-level `\\`, ignoring nested environments like `matrix`.
+fn rows(content: &str, span: Span) -> Vec<Row> {
+    let mut rows = Vec::new();
+    let mut depth = 0usize;
+    let mut row_start = 0;
+    let mut ampersands = 0;
+    let mut chars = content.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => match chars.peek() {
+                Some(&(_, '\\')) if depth == 0 => {
+                    chars.next();
+                    rows.push(Row { span: Span { start: span.start + row_start, end: span.start + i + 2, ..span }, ampersands });
+                    row_start = i + 2;
+                    ampersands = 0;
+                },
+                Some(_) if content[i..].starts_with("\\begin{") => depth += 1,
+                Some(_) if content[i..].starts_with("\\end{") => depth = depth.saturating_sub(1),
+                // escaped character like `\&`
+                Some(_) => drop(chars.next()),
+                None => (),
+            },
+            '&' if depth == 0 => ampersands += 1,
+            _ => (),
+        }
+    }
+    if !content[row_start..].trim().is_empty() {
+        rows.push(Row { span: Span { start: span.start + row_start, end: span.start + content.len(), ..span }, ampersands });
+    }
+    rows
+}
+
+fn validate(rows: &[Row], env: EquationEnvironment, span: Span, diagnostics: &Diagnostics) {
+    let max_ampersands = match env {
+        EquationEnvironment::Align | EquationEnvironment::Split => None,
+        EquationEnvironment::Gather | EquationEnvironment::Multline => Some(0),
+        EquationEnvironment::Cases => Some(1),
+    };
+    if let Some(max) = max_ampersands {
+        for row in rows.iter().filter(|row| row.ampersands > max) {
+            diagnostics
+                .warning(DiagnosticCode::EquationEnvironmentMismatch)
+                .with_error_label(row.span, format!("this row contains {} `&`", row.ampersands))
+                .with_note(match env {
+                    EquationEnvironment::Cases => {
+                        "`cases` only supports a single `&` per row separating the value and its condition"
+                    },
+                    _ => "this environment doesn't support alignment with `&`",
+                })
+                .with_note("try using `env=align` instead")
+                .emit();
+        }
+    }
+    if env == EquationEnvironment::Multline && rows.len() < 2 {
+        diagnostics
+            .warning(DiagnosticCode::EquationEnvironmentMismatch)
+            .with_error_label(span, "this equation only consists of a single line")
+            .with_note("`multline` is meant for equations split into multiple lines with `\\\\`")
+            .emit();
+    }
+}
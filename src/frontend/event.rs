@@ -1,10 +1,12 @@
 use std::borrow::Cow;
-use diagnostic::Spanned;
+use diagnostic::{Span, Spanned};
 
 pub use pulldown_cmark::Alignment;
 
 use enum_kinds::EnumKind;
+use strum_macros::{Display, EnumString};
 
+use super::chart::ChartSpec;
 use crate::resolve::{Command, ResolveSecurity};
 
 // extension of pulldown_cmark::Event with custom types
@@ -16,6 +18,8 @@ pub enum Event<'a> {
     Text(Cow<'a, str>),
     Html(Cow<'a, str>),
     Latex(Cow<'a, str>),
+    /// Reviewer note written as `<!-- note: … -->`, only rendered with `show_comments`
+    Comment(Cow<'a, str>),
     FootnoteReference(FootnoteReference<'a>),
     BiberReferences(Vec<BiberReference<'a>>),
     /// Url without content
@@ -26,6 +30,8 @@ pub enum Event<'a> {
     Include(Include<'a>),
     /// Include to be resolved by the resolver
     ResolveInclude(Cow<'a, str>),
+    /// Chart whose data file needs to be resolved before it can be rendered
+    Chart(Chart<'a>),
     Label(Cow<'a, str>),
     SoftBreak,
     HardBreak,
@@ -83,9 +89,9 @@ pub enum Tag<'a> {
 
     TableFigure(Figure<'a>),
     Table(Table<'a>),
-    TableHead,
-    TableRow,
-    TableCell,
+    TableHead(TableHead),
+    TableRow(TableRow),
+    TableCell(TableCell),
 
     InlineEmphasis,
     InlineStrong,
@@ -96,6 +102,8 @@ pub enum Tag<'a> {
     Equation(Equation<'a>),
     NumberedEquation(Equation<'a>),
     Graphviz(Graphviz<'a>),
+    Gnuplot(Gnuplot<'a>),
+    Tikz(Tikz<'a>),
 }
 
 #[derive(Debug, Clone)]
@@ -110,6 +118,19 @@ pub struct CodeBlock<'a> {
     pub caption: Option<Spanned<Cow<'a, str>>>,
     pub language: Option<Spanned<Cow<'a, str>>>,
     pub basicstyle: Option<Spanned<Cow<'a, str>>>,
+    pub style: CodeStyle,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CodeStyle {
+    /// Show (`Some(true)`) or hide (`Some(false)`) line numbers, `None` for the default
+    pub linenos: Option<bool>,
+    /// Number of the first line
+    pub firstline: Option<usize>,
+    /// Inclusive ranges of highlighted lines, using the printed line numbers
+    pub highlight: Vec<(usize, usize)>,
+    /// If `$…$` within the code is rendered as LaTeX math
+    pub mathescape: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -135,7 +156,50 @@ pub struct ColumnWidthPercent(pub f32);
 pub struct Table<'a> {
     pub label: Option<Spanned<Cow<'a, str>>>,
     pub caption: Option<Spanned<Cow<'a, str>>>,
-    pub columns: Vec<(Alignment, ColumnWidthPercent)>
+    pub columns: Vec<(Alignment, ColumnWidthPercent)>,
+    pub style: TableStyle<'a>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TableStyle<'a> {
+    /// Only horizontal rules from booktabs instead of a grid
+    pub booktabs: bool,
+    /// Background color of every second row
+    pub zebra: Option<Cow<'a, str>>,
+    /// LaTeX font size like `small`
+    pub font_size: Option<Cow<'a, str>>,
+    /// Allow the table to break across pages, repeating the head
+    pub longtable: bool,
+    /// Relative column widths like `1 2 1`, already applied to the columns by the frontend
+    pub widths: Option<Spanned<Cow<'a, str>>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TableHead {
+    pub booktabs: bool,
+    pub longtable: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct TableRow {
+    /// For each column if it needs a horizontal rule below this row, which isn't the case for
+    /// cells spanning into the next row
+    pub rules_below: Vec<bool>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TableCell {
+    /// Index of the (first) column of this cell
+    pub column: usize,
+    pub colspan: usize,
+    pub rowspan: usize,
+    /// If this cell is covered by a cell of a row above spanning multiple rows
+    pub covered: bool,
+    pub alignment: Alignment,
+    /// Width of all spanned columns
+    pub width: ColumnWidthPercent,
+    /// If the table doesn't have vertical rules
+    pub booktabs: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -150,12 +214,58 @@ pub struct Include<'a> {
     pub scale: Option<Spanned<Cow<'a, str>>>,
     pub width: Option<Spanned<Cow<'a, str>>>,
     pub height: Option<Spanned<Cow<'a, str>>>,
+    /// CSV / TSV only: if the first row is the table head, disabled with `noheader`
+    pub header: bool,
+    /// CSV / TSV only: alignment of the columns like `lcr`
+    pub align: Option<Spanned<Cow<'a, str>>>,
+    /// CSV / TSV only: style of the table
+    pub table_style: TableStyle<'a>,
+    /// Source code only: language overriding the one inferred from the file extension
+    pub language: Option<Spanned<Cow<'a, str>>>,
+    /// Source code only: included line range like `10-42`
+    pub lines: Option<Spanned<Cow<'a, str>>>,
+    /// Source code only: included region between `ANCHOR: name` and `ANCHOR_END: name`
+    pub region: Option<Spanned<Cow<'a, str>>>,
+    /// Source code only: if the common indentation is removed, disabled with `nodedent`
+    pub dedent: bool,
+    /// Source code only: line numbers and highlighted lines
+    pub code_style: CodeStyle,
+}
+
+#[derive(Debug, Clone)]
+pub struct Chart<'a> {
+    pub spec: ChartSpec,
+    /// Include of the data file with the config of the code block
+    pub include: Include<'a>,
+    /// Span of the line giving the data file
+    pub data_span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub struct Equation<'a> {
     pub label: Option<Spanned<Cow<'a, str>>>,
     pub caption: Option<Spanned<Cow<'a, str>>>,
+    pub environment: EquationEnvironment,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum EquationEnvironment {
+    Align,
+    Gather,
+    Multline,
+    Split,
+    Cases,
+}
+
+impl EquationEnvironment {
+    /// If every row gets its own number in numbered equations.
+    pub fn numbers_rows(self) -> bool {
+        match self {
+            EquationEnvironment::Align | EquationEnvironment::Gather => true,
+            EquationEnvironment::Multline | EquationEnvironment::Split | EquationEnvironment::Cases => false,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -165,4 +275,61 @@ pub struct Graphviz<'a> {
     pub scale: Option<Spanned<Cow<'a, str>>>,
     pub width: Option<Spanned<Cow<'a, str>>>,
     pub height: Option<Spanned<Cow<'a, str>>>,
+    pub engine: GraphvizEngine,
+    pub format: GraphvizFormat,
+    /// Graph attributes like `rankdir=LR` given in the element config
+    pub attributes: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+    /// Span of the graph, used to point at the failing line on errors
+    pub code_span: Option<Span>,
+}
+
+/// Layout program of graphviz
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum GraphvizEngine {
+    Dot,
+    Neato,
+    Circo,
+    Fdp,
+    Twopi,
+    Sfdp,
+}
+
+/// Format graphviz renders to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum GraphvizFormat {
+    Pdf,
+    /// Rendered to svg and converted to pdf like other svgs
+    Svg,
+}
+
+#[derive(Debug, Clone)]
+pub struct Gnuplot<'a> {
+    pub label: Option<Spanned<Cow<'a, str>>>,
+    pub caption: Option<Spanned<Cow<'a, str>>>,
+    pub scale: Option<Spanned<Cow<'a, str>>>,
+    pub width: Option<Spanned<Cow<'a, str>>>,
+    pub height: Option<Spanned<Cow<'a, str>>>,
+    pub terminal: GnuplotTerminal,
+    /// Span of the gnuplot script, used to point at the failing line on errors
+    pub script_span: Option<Span>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Tikz<'a> {
+    pub label: Option<Spanned<Cow<'a, str>>>,
+    pub caption: Option<Spanned<Cow<'a, str>>>,
+    pub scale: Option<Spanned<Cow<'a, str>>>,
+    pub width: Option<Spanned<Cow<'a, str>>>,
+    pub height: Option<Spanned<Cow<'a, str>>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum GnuplotTerminal {
+    /// Text is typeset by LaTeX, graphics are rendered to pdf
+    Cairolatex,
+    /// Everything is rendered to pdf by gnuplot
+    Pdfcairo,
 }
@@ -0,0 +1,129 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::cache::{self, CacheKey};
+
+/// Environment variable containing the directory images must be written to.
+const OUT_DIR_VAR: &str = "HERADOC_OUT";
+/// Image files written by executed code, which are included after its output.
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "svg", "pdf"];
+
+/// Captured result of executing a code block.
+#[derive(Debug)]
+pub struct Executed {
+    pub stdout: String,
+    /// Images written to the directory in `HERADOC_OUT`, sorted by name
+    pub images: Vec<PathBuf>,
+}
+
+/// Executes the code with the given interpreter command, appending the path of the code file.
+///
+/// The code runs in the working directory, usually the folder of the document, such that it can
+/// read files next to the document. Images must be written to the directory in the environment
+/// variable `HERADOC_OUT`, which is the code's own entry of the cache directory.
+///
+/// The entry is keyed by the interpreter, the working directory and the code. If it contains the
+/// stdout of a successful previous run, the code isn't executed again. Files read by the code
+/// aren't part of the key.
+pub fn execute(cache_dir: &Path, working_dir: &Path, interpreter: &[&str], code: &str) -> Result<Executed, String> {
+    let key = CacheKey::new("exec")
+        .with(interpreter.join(" "))
+        .with(working_dir.to_string_lossy().as_bytes())
+        .with(code);
+    let stdout_path = cache::cached(cache_dir, key, |dir| {
+        // the code runs in another directory
+        let dir = fs::canonicalize(dir)?;
+        let out_dir = dir.join("out");
+        fs::create_dir(&out_dir)?;
+        let code_path = dir.join("code");
+        fs::write(&code_path, code)?;
+        let output = Command::new(interpreter[0])
+            .args(&interpreter[1..])
+            .arg(&code_path)
+            .current_dir(working_dir)
+            .env(OUT_DIR_VAR, &out_dir)
+            .output()
+            .map_err(|e| io::Error::new(e.kind(), format!("can't run `{}`: {}", interpreter[0], e)))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("`{}` failed with {}:\n{}", interpreter.join(" "), output.status, stderr.trim_end()),
+            ));
+        }
+        let stdout_path = dir.join("stdout");
+        fs::write(&stdout_path, &output.stdout)?;
+        Ok(stdout_path)
+    })
+    .map_err(|e| e.to_string())?;
+    let dir = stdout_path.parent().expect("cached artifact is within its directory").join("out");
+
+    let stdout = fs::read(&stdout_path).map_err(|e| format!("can't read output {:?}: {}", stdout_path, e))?;
+    let mut images: Vec<_> = fs::read_dir(&dir)
+        .map_err(|e| format!("can't read directory {:?}: {}", dir, e))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .map_or(false, |ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        })
+        .collect();
+    images.sort();
+    Ok(Executed { stdout: String::from_utf8_lossy(&stdout).into_owned(), images })
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use tempdir::TempDir;
+
+    use super::execute;
+
+    #[test]
+    fn test_cache_hit() {
+        let tmpdir = TempDir::new("heradoc-test").expect("Can't create tempdir");
+        let counter = tmpdir.path().join("counter");
+        let code = format!("echo run >> '{}'\necho output", counter.display());
+        for _ in 0..2 {
+            let executed = execute(&tmpdir.path().join("cache"), tmpdir.path(), &["sh"], &code).unwrap();
+            assert_eq!(executed.stdout, "output\n");
+        }
+        assert_eq!(fs::read_to_string(&counter).unwrap(), "run\n");
+    }
+
+    #[test]
+    fn test_failing_interpreter() {
+        let tmpdir = TempDir::new("heradoc-test").expect("Can't create tempdir");
+        let cache_dir = tmpdir.path().join("cache");
+        let error = execute(&cache_dir, tmpdir.path(), &["sh"], "echo failure >&2\nexit 3").unwrap_err();
+        assert!(error.contains("failure"), "{}", error);
+        // failed runs aren't cached
+        assert_eq!(fs::read_dir(&cache_dir).unwrap().count(), 0);
+        assert!(execute(&cache_dir, tmpdir.path(), &["heradoc-nonexistent-interpreter"], "").is_err());
+    }
+
+    #[test]
+    fn test_images() {
+        let tmpdir = TempDir::new("heradoc-test").expect("Can't create tempdir");
+        let code = "cd \"$HERADOC_OUT\"\ntouch b.svg a.PNG plot.txt";
+        let executed = execute(&tmpdir.path().join("cache"), tmpdir.path(), &["sh"], code).unwrap();
+        let names: Vec<_> = executed.images.iter()
+            .map(|path| path.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(names, ["a.PNG", "b.svg"]);
+    }
+
+    #[test]
+    fn test_working_dir() {
+        let tmpdir = TempDir::new("heradoc-test").expect("Can't create tempdir");
+        fs::write(tmpdir.path().join("data.txt"), "data\n").unwrap();
+        let executed = execute(&tmpdir.path().join("cache"), tmpdir.path(), &["sh"], "cat data.txt").unwrap();
+        assert_eq!(executed.stdout, "data\n");
+        // images written to the working directory aren't included
+        let executed = execute(&tmpdir.path().join("cache"), tmpdir.path(), &["sh"], "touch a.svg").unwrap();
+        assert!(executed.images.is_empty());
+    }
+}
@@ -0,0 +1,409 @@
+//! Flowcharts in the syntax of flowchart.js, rendered to SVG.
+//!
+//! ```text
+//! st=>start: Start
+//! op=>operation: Do something
+//! cond=>condition: Worked?
+//! e=>end: End
+//!
+//! st->op->cond
+//! cond(yes)->e
+//! cond(no, right)->op
+//! ```
+//!
+//! Nodes are placed on a grid: the target of a connection is placed below its source, or in the
+//! direction given in the connection, unless it's already placed.
+
+use std::collections::HashSet;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::svg::{text_height, text_width, unescape, Anchor, ParseError, SvgBuilder, LINE_HEIGHT};
+
+/// Padding between the border of a node and its text
+const PADDING: f64 = 8.0;
+/// Space between columns and rows of the grid
+const COLUMN_GAP: f64 = 40.0;
+const ROW_GAP: f64 = 40.0;
+/// Distance between lanes of connections routed around the chart
+const LANE_GAP: f64 = 15.0;
+/// Horizontal offset of the slanted sides of input / output nodes
+const SKEW: f64 = 10.0;
+
+#[derive(Debug, PartialEq)]
+pub struct Flowchart {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Node {
+    id: String,
+    kind: NodeKind,
+    text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NodeKind {
+    Start,
+    End,
+    Operation,
+    Subroutine,
+    Condition,
+    InputOutput,
+    Parallel,
+}
+
+#[derive(Debug, PartialEq)]
+struct Edge {
+    from: usize,
+    to: usize,
+    /// `yes` / `no` of conditions or `path1` to `path3` of parallel nodes
+    label: Option<String>,
+    direction: Option<Direction>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Top,
+    Right,
+    Bottom,
+    Left,
+}
+
+impl Direction {
+    fn offset(self) -> (i32, i32) {
+        match self {
+            Direction::Top => (0, -1),
+            Direction::Right => (1, 0),
+            Direction::Bottom => (0, 1),
+            Direction::Left => (-1, 0),
+        }
+    }
+}
+
+impl Flowchart {
+    pub fn parse(src: &str) -> Result<Flowchart, ParseError> {
+        lazy_static! {
+            static ref NODE: Regex = Regex::new(
+                r"^(\w+)\s*=>\s*(start|end|operation|subroutine|condition|inputoutput|parallel)\s*(?::\s*(.*))?$"
+            ).unwrap();
+            // suffixes of the text: state like `|past` and link like `:>http://example.com[blank]`
+            static ref STATE: Regex = Regex::new(r"\|\w+$").unwrap();
+            static ref LINK: Regex = Regex::new(r":>\S*$").unwrap();
+            static ref STEP: Regex = Regex::new(r"^(\w+)\s*(?:\(([^)]*)\))?$").unwrap();
+        }
+
+        let mut chart = Flowchart { nodes: Vec::new(), edges: Vec::new() };
+        let mut connections = Vec::new();
+        for (i, line) in src.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(captures) = NODE.captures(line) {
+                let id = &captures[1];
+                if chart.nodes.iter().any(|node| node.id == id) {
+                    return Err(ParseError { line: i, message: format!("node `{}` defined multiple times", id) });
+                }
+                let kind = match &captures[2] {
+                    "start" => NodeKind::Start,
+                    "end" => NodeKind::End,
+                    "operation" => NodeKind::Operation,
+                    "subroutine" => NodeKind::Subroutine,
+                    "condition" => NodeKind::Condition,
+                    "inputoutput" => NodeKind::InputOutput,
+                    "parallel" => NodeKind::Parallel,
+                    _ => unreachable!(),
+                };
+                let text = captures.get(3).map_or("", |text| text.as_str());
+                let text = LINK.replace(text, "");
+                let text = STATE.replace(&text, "");
+                chart.nodes.push(Node { id: id.to_string(), kind, text: unescape(&text) });
+            } else if line.contains("->") {
+                // connections can reference nodes defined after them
+                connections.push((i, line));
+            } else {
+                let message = "expected a node like `op=>operation: text` or a connection like `a->b`".to_string();
+                return Err(ParseError { line: i, message });
+            }
+        }
+
+        for (i, line) in connections {
+            let mut steps = Vec::new();
+            for step in line.split("->") {
+                let captures = STEP.captures(step.trim()).ok_or_else(|| {
+                    ParseError { line: i, message: format!("invalid step `{}`", step.trim()) }
+                })?;
+                let node = chart.nodes.iter().position(|node| node.id == &captures[1]).ok_or_else(|| {
+                    ParseError { line: i, message: format!("unknown node `{}`", &captures[1]) }
+                })?;
+                let mut label = None;
+                let mut direction = None;
+                for arg in captures.get(2).map_or("", |args| args.as_str()).split(',') {
+                    match arg.trim() {
+                        "" => (),
+                        "top" => direction = Some(Direction::Top),
+                        "right" => direction = Some(Direction::Right),
+                        "bottom" => direction = Some(Direction::Bottom),
+                        "left" => direction = Some(Direction::Left),
+                        arg @ "yes" | arg @ "no" | arg @ "path1" | arg @ "path2" | arg @ "path3" => {
+                            label = Some(arg.to_string());
+                        },
+                        arg => {
+                            let message = format!("unknown option `{}`, expected a direction or branch", arg);
+                            return Err(ParseError { line: i, message });
+                        },
+                    }
+                }
+                steps.push((node, label, direction));
+            }
+            for pair in steps.windows(2) {
+                let (from, ref label, direction) = pair[0];
+                chart.edges.push(Edge { from, to: pair[1].0, label: label.clone(), direction });
+            }
+        }
+
+        if chart.nodes.is_empty() {
+            return Err(ParseError { line: 0, message: "flowchart doesn't contain any nodes".to_string() });
+        }
+        Ok(chart)
+    }
+
+    /// Direction in which the edge leaves its source.
+    fn direction(&self, edge: &Edge) -> Direction {
+        edge.direction.unwrap_or_else(|| match edge.label.as_ref().map(String::as_str) {
+            Some("no") | Some("path2") => Direction::Right,
+            Some("path3") => Direction::Top,
+            _ => Direction::Bottom,
+        })
+    }
+
+    /// Grid cells (column, row) of all nodes, starting with the first `start` node.
+    fn layout_grid(&self) -> Vec<(i32, i32)> {
+        let mut cells: Vec<Option<(i32, i32)>> = vec![None; self.nodes.len()];
+        let mut occupied = HashSet::new();
+        let first = self.nodes.iter().position(|node| node.kind == NodeKind::Start).unwrap_or(0);
+        let mut roots = vec![first];
+        roots.extend(0..self.nodes.len());
+        for root in roots {
+            if cells[root].is_some() {
+                continue;
+            }
+            // unconnected parts of the chart are placed to the right of everything else
+            let column = occupied.iter().map(|&(column, _)| column + 1).max().unwrap_or(0);
+            cells[root] = Some((column, 0));
+            occupied.insert((column, 0));
+            let mut stack = vec![root];
+            while let Some(node) = stack.pop() {
+                let (column, row) = cells[node].unwrap();
+                let mut placed = Vec::new();
+                for edge in self.edges.iter().filter(|edge| edge.from == node) {
+                    if cells[edge.to].is_some() {
+                        continue;
+                    }
+                    let (dx, dy) = self.direction(edge).offset();
+                    let mut cell = (column + dx, row + dy);
+                    while occupied.contains(&cell) {
+                        cell = (cell.0 + dx, cell.1 + dy);
+                    }
+                    cells[edge.to] = Some(cell);
+                    occupied.insert(cell);
+                    placed.push(edge.to);
+                }
+                // reversed, such that the targets of the first edges are continued first
+                stack.extend(placed.into_iter().rev());
+            }
+        }
+        cells.into_iter().map(Option::unwrap).collect()
+    }
+
+    pub fn to_svg(&self) -> String {
+        let cells = self.layout_grid();
+        let min_column = cells.iter().map(|c| c.0).min().unwrap();
+        let min_row = cells.iter().map(|c| c.1).min().unwrap();
+        let cells: Vec<(usize, usize)> = cells.iter()
+            .map(|&(column, row)| ((column - min_column) as usize, (row - min_row) as usize))
+            .collect();
+        let sizes: Vec<(f64, f64)> = self.nodes.iter().map(Node::size).collect();
+
+        // center of each column and row
+        let columns = cells.iter().map(|c| c.0).max().unwrap() + 1;
+        let rows = cells.iter().map(|c| c.1).max().unwrap() + 1;
+        let mut widths = vec![0.0f64; columns];
+        let mut heights = vec![0.0f64; rows];
+        for (&(column, row), &(width, height)) in cells.iter().zip(&sizes) {
+            widths[column] = widths[column].max(width);
+            heights[row] = heights[row].max(height);
+        }
+        let centers = |sizes: &[f64], gap: f64| -> Vec<f64> {
+            let mut pos = 0.0;
+            sizes.iter().map(|size| {
+                let center = pos + size / 2.0;
+                pos += size + gap;
+                center
+            }).collect()
+        };
+        let xs = centers(&widths, COLUMN_GAP);
+        let ys = centers(&heights, ROW_GAP);
+        let position = |node: usize| (xs[cells[node].0], ys[cells[node].1]);
+        let port = |node: usize, direction: Direction| {
+            let (x, y) = position(node);
+            let (width, height) = sizes[node];
+            match direction {
+                Direction::Top => (x, y - height / 2.0),
+                Direction::Right => (x + width / 2.0, y),
+                Direction::Bottom => (x, y + height / 2.0),
+                Direction::Left => (x - width / 2.0, y),
+            }
+        };
+        let right = xs.last().unwrap() + widths.last().unwrap() / 2.0;
+        let left = xs[0] - widths[0] / 2.0;
+        let mut right_lanes = 0;
+        let mut left_lanes = 0;
+
+        let mut svg = SvgBuilder::new();
+        for (i, node) in self.nodes.iter().enumerate() {
+            let (x, y) = position(i);
+            node.draw(&mut svg, x, y, sizes[i]);
+        }
+        for edge in &self.edges {
+            let direction = self.direction(edge);
+            let (from, to) = (cells[edge.from], cells[edge.to]);
+            let start = port(edge.from, direction);
+            let (tx, ty) = position(edge.to);
+            let points = match direction {
+                Direction::Bottom if from.0 == to.0 && to.1 > from.1 => vec![start, port(edge.to, Direction::Top)],
+                Direction::Top if from.0 == to.0 && to.1 < from.1 => vec![start, port(edge.to, Direction::Bottom)],
+                Direction::Right if from.1 == to.1 && to.0 > from.0 => vec![start, port(edge.to, Direction::Left)],
+                Direction::Left if from.1 == to.1 && to.0 < from.0 => vec![start, port(edge.to, Direction::Right)],
+                // vertical, then horizontal between the rows, then vertical again
+                Direction::Bottom | Direction::Top if (direction == Direction::Bottom) == (to.1 > from.1) && to.1 != from.1 => {
+                    let between = if direction == Direction::Bottom {
+                        ys[from.1] + heights[from.1] / 2.0 + ROW_GAP / 2.0
+                    } else {
+                        ys[from.1] - heights[from.1] / 2.0 - ROW_GAP / 2.0
+                    };
+                    let end = if direction == Direction::Bottom { Direction::Top } else { Direction::Bottom };
+                    vec![start, (start.0, between), (tx, between), port(edge.to, end)]
+                },
+                // horizontal, then vertical
+                Direction::Right | Direction::Left if (direction == Direction::Right) == (to.0 > from.0) && to.0 != from.0 => {
+                    let end = if to.1 > from.1 { Direction::Top } else { Direction::Bottom };
+                    vec![start, (tx, start.1), port(edge.to, end)]
+                },
+                // around everything else on the left or right side
+                _ => {
+                    let (lane, end) = if direction == Direction::Left {
+                        left_lanes += 1;
+                        (left - left_lanes as f64 * LANE_GAP, Direction::Left)
+                    } else {
+                        right_lanes += 1;
+                        (right + right_lanes as f64 * LANE_GAP, Direction::Right)
+                    };
+                    let mut points = vec![start];
+                    match direction {
+                        Direction::Bottom => points.push((start.0, start.1 + ROW_GAP / 4.0)),
+                        Direction::Top => points.push((start.0, start.1 - ROW_GAP / 4.0)),
+                        Direction::Left | Direction::Right => (),
+                    }
+                    let last = *points.last().unwrap();
+                    points.push((lane, last.1));
+                    points.push((lane, ty));
+                    points.push(port(edge.to, end));
+                    points
+                },
+            };
+            svg.polyline(&points, r#"marker-end="url(#arrow)""#);
+
+            if let Some(label) = &edge.label {
+                let (x, y) = start;
+                match direction {
+                    Direction::Bottom => svg.text(x + 4.0, y, label, Anchor::Start),
+                    Direction::Left => svg.text(x - 4.0, y - LINE_HEIGHT, label, Anchor::End),
+                    Direction::Top | Direction::Right => svg.text(x + 4.0, y - LINE_HEIGHT, label, Anchor::Start),
+                }
+            }
+        }
+        svg.finish()
+    }
+}
+
+impl Node {
+    /// Width and height of the node.
+    fn size(&self) -> (f64, f64) {
+        let (width, height) = (text_width(&self.text) + 2.0 * PADDING, text_height(&self.text) + 2.0 * PADDING);
+        match self.kind {
+            // the text must fit within the diamond
+            NodeKind::Condition => (1.5 * width, 3.0 * text_height(&self.text)),
+            NodeKind::InputOutput => (width + 2.0 * SKEW, height),
+            NodeKind::Subroutine => (width + 2.0 * PADDING, height),
+            _ => (width, height),
+        }
+    }
+
+    fn draw(&self, svg: &mut SvgBuilder, x: f64, y: f64, (width, height): (f64, f64)) {
+        let (left, top) = (x - width / 2.0, y - height / 2.0);
+        let (right, bottom) = (left + width, top + height);
+        match self.kind {
+            NodeKind::Start | NodeKind::End => svg.rounded_rect(left, top, width, height, height / 2.0, "white"),
+            NodeKind::Operation | NodeKind::Parallel => svg.rect(left, top, width, height, "white"),
+            NodeKind::Subroutine => {
+                svg.rect(left, top, width, height, "white");
+                svg.polyline(&[(left + PADDING, top), (left + PADDING, bottom)], "");
+                svg.polyline(&[(right - PADDING, top), (right - PADDING, bottom)], "");
+            },
+            NodeKind::Condition => svg.polygon(&[(x, top), (right, y), (x, bottom), (left, y)], ""),
+            NodeKind::InputOutput => {
+                let points = [(left + SKEW, top), (right, top), (right - SKEW, bottom), (left, bottom)];
+                svg.polygon(&points, "");
+            },
+        }
+        svg.text(x, y - text_height(&self.text) / 2.0, &self.text, Anchor::Middle);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SRC: &str = "st=>start: Start|past:>http://example.com[blank]
+op=>operation: My Operation
+cond=>condition: Yes\\nor No?
+e=>end: End
+
+st->op->cond
+cond(yes)->e
+cond(no, left)->op";
+
+    #[test]
+    fn test_parse() {
+        let chart = Flowchart::parse(SRC).unwrap();
+        assert_eq!(chart.nodes[0], Node { id: "st".to_string(), kind: NodeKind::Start, text: "Start".to_string() });
+        assert_eq!(chart.nodes[2].text, "Yes\nor No?");
+        assert_eq!(chart.edges, vec![
+            Edge { from: 0, to: 1, label: None, direction: None },
+            Edge { from: 1, to: 2, label: None, direction: None },
+            Edge { from: 2, to: 3, label: Some("yes".to_string()), direction: None },
+            Edge { from: 2, to: 1, label: Some("no".to_string()), direction: Some(Direction::Left) },
+        ]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Flowchart::parse("a=>start: A\na->b").unwrap_err().line, 1);
+        assert_eq!(Flowchart::parse("a=>start: A\n\na=>end: B").unwrap_err().line, 2);
+        assert_eq!(Flowchart::parse("a=>foo: A").unwrap_err().line, 0);
+        assert_eq!(Flowchart::parse("a=>start: A\na(sideways)->a").unwrap_err().line, 1);
+    }
+
+    #[test]
+    fn test_layout() {
+        let chart = Flowchart::parse(SRC).unwrap();
+        assert_eq!(chart.layout_grid(), vec![(0, 0), (0, 1), (0, 2), (0, 3)]);
+        let chart = Flowchart::parse("a=>start: A\nb=>end: B\nc=>end: C\nd=>end: D\na(right)->b\na->c\nb->d").unwrap();
+        assert_eq!(chart.layout_grid(), vec![(0, 0), (1, 0), (0, 1), (1, 1)]);
+        assert!(chart.to_svg().contains(">D</text>"));
+    }
+}
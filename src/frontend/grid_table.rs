@@ -0,0 +1,233 @@
+use std::borrow::Cow;
+use diagnostic::{Span, Spanned};
+
+use lazy_static::lazy_static;
+use pulldown_cmark::Alignment;
+use regex::Regex;
+
+use super::convert_cow::{Event, Tag};
+use super::math::DollarMath;
+
+lazy_static! {
+    // Matches border lines like `+---+:--:+` and the head separator `+===+===+`
+    static ref BORDER: Regex = Regex::new(r"^\+(:?(-+|=+):?\+)+$").unwrap();
+}
+
+/// Replaces paragraphs consisting of a pandoc-style grid table with a single grid table event.
+///
+/// Pulldown-cmark parses grid tables as normal paragraphs. As the cells can contain
+/// block-level content, the raw source is passed on and each cell is parsed separately.
+pub struct GridTables<'a> {
+    inner: DollarMath<'a>,
+    source: &'a str,
+    offset: usize,
+}
+
+impl<'a> GridTables<'a> {
+    /// `source` must be the string of the file the spans of `inner` point into, starting at byte
+    /// `offset` of the file.
+    pub fn new(source: &'a str, offset: usize, inner: DollarMath<'a>) -> GridTables<'a> {
+        GridTables { inner, source, offset }
+    }
+}
+
+impl<'a> Iterator for GridTables<'a> {
+    type Item = Spanned<Event<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let evt = self.inner.next()?;
+        let span = match evt {
+            Spanned { value: Event::Start(Tag::Paragraph), span } => span,
+            evt => return Some(evt),
+        };
+        let table = self.source[span.start - self.offset..span.end - self.offset].trim_end();
+        let first = table.lines().next().unwrap_or("").trim();
+        let last = table.lines().last().unwrap_or("").trim();
+        if !(BORDER.is_match(first) && BORDER.is_match(last) && table.lines().count() >= 3) {
+            return Some(Spanned::new(Event::Start(Tag::Paragraph), span));
+        }
+        // swallow the paragraph content
+        loop {
+            match self.inner.next().unwrap().value {
+                Event::End(Tag::Paragraph) => break,
+                _ => (),
+            }
+        }
+        let span = Span { end: span.start + table.len(), ..span };
+        Some(Spanned::new(Event::GridTable(Cow::Borrowed(table)), span))
+    }
+}
+
+/// Parsed grid table with the markdown content of each cell.
+#[derive(Debug, PartialEq)]
+pub struct GridTable {
+    pub alignment: Vec<Alignment>,
+    /// Number of rows above the head separator `+===+`, either 0 or 1
+    pub head_rows: usize,
+    pub rows: Vec<Vec<String>>,
+    /// Byte offset within the table of every line of each cell
+    pub line_offsets: Vec<Vec<Vec<usize>>>,
+}
+
+/// Parses a grid table like the following:
+///
+/// ```md
+/// +-------+:------:+
+/// | Head  | Center |
+/// +=======+========+
+/// | - a   | text   |
+/// | - b   |        |
+/// +-------+--------+
+/// ```
+///
+/// Column borders are taken from the first line and must be the same in all lines.
+/// The alignment is given by colons in the first line or the head separator.
+pub fn parse(table: &str) -> Result<GridTable, String> {
+    // byte offset within the table of every character
+    let mut offsets = Vec::new();
+    let mut line_start = 0;
+    let lines: Vec<Vec<char>> = table
+        .split('\n')
+        .map(|line| {
+            let indent = line.len() - line.trim_start().len();
+            offsets.push(line.trim().char_indices().map(|(i, _)| line_start + indent + i).collect::<Vec<_>>());
+            line_start += line.len() + 1;
+            line.trim().chars().collect()
+        })
+        .collect();
+    let borders: Vec<usize> = lines[0].iter().enumerate().filter(|&(_, &c)| c == '+').map(|(i, _)| i).collect();
+    let mut alignment = alignment(&lines[0], &borders);
+    let mut head_rows = 0;
+    let mut rows = Vec::new();
+    let mut line_offsets = Vec::new();
+    let mut row: Vec<Vec<(usize, String)>> = Vec::new();
+
+    for (i, line) in lines.iter().enumerate().skip(1) {
+        let border = line.first() == Some(&'+');
+        let separator = if border { '+' } else { '|' };
+        let matches = line.len() == borders[borders.len() - 1] + 1
+            && borders.iter().all(|&b| line[b] == separator)
+            && (!border || BORDER.is_match(&line.iter().collect::<String>()))
+            && (!border || line.iter().enumerate().all(|(j, &c)| c != '+' || borders.contains(&j)));
+        if !matches {
+            return Err(format!("line {} doesn't match the column borders of the first line", i + 1));
+        }
+        if !border {
+            if row.is_empty() {
+                row = vec![Vec::new(); borders.len() - 1];
+            }
+            for (cell, window) in row.iter_mut().zip(borders.windows(2)) {
+                cell.push((offsets[i][window[0] + 1], line[window[0] + 1..window[1]].iter().collect()));
+            }
+            continue;
+        }
+        if row.is_empty() {
+            return Err(format!("line {} is a border without a row above it", i + 1));
+        }
+        let (texts, offsets): (Vec<_>, Vec<_>) = row.drain(..).map(|lines| dedent(&lines)).unzip();
+        rows.push(texts);
+        line_offsets.push(offsets);
+        if line.contains(&'=') {
+            if head_rows != 0 || rows.len() != 1 {
+                return Err(format!("line {}: only the first row can be separated as head with `=`", i + 1));
+            }
+            head_rows = 1;
+            if line.contains(&':') {
+                alignment = self::alignment(line, &borders);
+            }
+        }
+    }
+    Ok(GridTable { alignment, head_rows, rows, line_offsets })
+}
+
+fn alignment(line: &[char], borders: &[usize]) -> Vec<Alignment> {
+    borders
+        .windows(2)
+        .map(|window| {
+            let left = line[window[0] + 1] == ':';
+            let right = line[window[1] - 1] == ':';
+            match (left, right) {
+                (false, false) => Alignment::None,
+                (true, false) => Alignment::Left,
+                (false, true) => Alignment::Right,
+                (true, true) => Alignment::Center,
+            }
+        })
+        .collect()
+}
+
+/// Joins the lines of a cell, removing leading and trailing blank lines and their common
+/// indentation.
+///
+/// Takes the offset of each line and returns the offsets of the joined lines.
+fn dedent(lines: &[(usize, String)]) -> (String, Vec<usize>) {
+    let lines: Vec<(usize, &str)> = lines.iter().map(|(offset, line)| (*offset, line.trim_end())).collect();
+    let start = lines.iter().position(|(_, line)| !line.is_empty()).unwrap_or(lines.len());
+    let end = lines.iter().rposition(|(_, line)| !line.is_empty()).map_or(start, |end| end + 1);
+    let lines = &lines[start..end];
+    let indent = lines
+        .iter()
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(_, line)| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let offsets = lines.iter().map(|(offset, line)| offset + indent.min(line.len())).collect();
+    let text = lines.iter().map(|(_, line)| line.get(indent..).unwrap_or("")).collect::<Vec<_>>().join("\n");
+    (text, offsets)
+}
+
+#[cfg(test)]
+mod test {
+    use pulldown_cmark::Alignment;
+
+    use super::{parse, GridTable};
+
+    #[test]
+    fn test_parse() {
+        let table = "\
++------+:------:+
+| Head | Center |
++======+========+
+| - a  | one    |
+| - b  |        |
+|      | two    |
++------+--------+
+| x    | y      |
++------+--------+";
+        assert_eq!(parse(table), Ok(GridTable {
+            alignment: vec![Alignment::None, Alignment::Center],
+            head_rows: 1,
+            rows: vec![
+                vec!["Head".to_string(), "Center".to_string()],
+                vec!["- a\n- b".to_string(), "one\n\ntwo".to_string()],
+                vec!["x".to_string(), "y".to_string()],
+            ],
+            line_offsets: vec![
+                vec![vec![20], vec![27]],
+                vec![vec![56, 74], vec![63, 80, 99]],
+                vec![vec![128], vec![135]],
+            ],
+        }));
+    }
+
+    #[test]
+    fn test_indentation() {
+        let table = "\
++------------+
+| ```rust    |
+| fn main() {|
+|     foo(); |
+| }          |
+| ```        |
++------------+";
+        assert_eq!(parse(table).unwrap().rows[0][0], "```rust\nfn main() {\n    foo();\n}\n```");
+        assert_eq!(parse(table).unwrap().head_rows, 0);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parse("+---+---+\n| a | b\n+---+---+").is_err());
+        assert!(parse("+---+---+\n| a | b | c |\n+---+---+").is_err());
+        assert!(parse("+---+\n| a |\n+===+\n| b |\n+===+").is_err());
+    }
+}
@@ -0,0 +1,306 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::ops::Range;
+use diagnostic::{Span, Spanned};
+
+use pulldown_cmark::{Event as CmarkEvent, Tag as CmarkTag};
+
+use super::convert_cow::{ConvertCow, Event, Tag};
+
+/// Replaces `$inline$` and `$$display$$` math in the markdown source with math events.
+///
+/// Pulldown-cmark doesn't know about dollar math and would interpret its content as markdown,
+/// e.g. `$a_1 * b_2*$` as emphasis. Thus the math ranges are searched in the raw source and all
+/// events overlapping them are replaced by a single math event containing the raw source.
+pub struct DollarMath<'a> {
+    inner: ConvertCow<'a>,
+    source: &'a str,
+    /// Byte of the file `source` starts at
+    offset: usize,
+    math: VecDeque<Range<usize>>,
+    /// If the current math range was already emitted
+    emitted: bool,
+    /// For every open tag if it was swallowed
+    open: Vec<bool>,
+    /// Number of open block quotes, whose `>` must be removed from math spanning multiple lines
+    quotes: usize,
+    buffer: VecDeque<Spanned<Event<'a>>>,
+}
+
+impl<'a> DollarMath<'a> {
+    /// Creates a new `DollarMath` wrapping `parser`.
+    ///
+    /// `markdown` must be the string of the file starting at byte `offset` of the file with the
+    /// span of the parsed markdown.
+    /// `code` must be a separate offset-iterator over the same markdown used to find code.
+    pub fn new(
+        markdown: Spanned<&'a str>, offset: usize, parser: ConvertCow<'a>,
+        code: impl Iterator<Item = (CmarkEvent<'a>, Range<usize>)>,
+    ) -> DollarMath<'a> {
+        let Spanned { value: source, span } = markdown;
+        let start = span.start - offset;
+        let code: Vec<_> = code
+            .filter(|(evt, _)| match evt {
+                CmarkEvent::Start(CmarkTag::CodeBlock(_)) | CmarkEvent::Code(_) | CmarkEvent::Html(_) => true,
+                _ => false,
+            })
+            .map(|(_, range)| range.start + start..range.end + start)
+            .collect();
+        let math = find_math(source, start..span.end - offset, &code)
+            .into_iter()
+            .map(|math| math.start + offset..math.end + offset)
+            .collect();
+        DollarMath {
+            inner: parser,
+            source,
+            offset,
+            math,
+            emitted: false,
+            open: Vec::new(),
+            quotes: 0,
+            buffer: VecDeque::new(),
+        }
+    }
+
+    fn handle(&mut self, Spanned { value: evt, span }: Spanned<Event<'a>>) {
+        // math ranges before this event were handled already
+        while self.math.front().map_or(false, |math| math.end <= span.start) {
+            self.math.pop_front();
+            self.emitted = false;
+        }
+        let math = match self.math.front() {
+            Some(math) if math.start < span.end => math.clone(),
+            _ => {
+                self.pass(Spanned::new(evt, span));
+                return;
+            },
+        };
+
+        // text overlapping the start or end of math must be split up
+        if let Event::Text(_) = evt {
+            if span.start < math.start {
+                let prefix = Span { start: span.start, end: math.start, ..span };
+                self.buffer.push_back(Spanned::new(Event::Text(Cow::Borrowed(self.source(span.start..math.start))), prefix));
+            }
+            self.emit_math(&math, span);
+            if span.end > math.end {
+                let suffix = Span { start: math.end, end: span.end, ..span };
+                self.handle(Spanned::new(Event::Text(Cow::Borrowed(self.source(math.end..span.end))), suffix));
+            }
+            return;
+        }
+
+        match evt {
+            // tags started outside of the math must be closed as usual
+            Event::End(_) if !self.open.pop().unwrap() => self.buffer.push_back(Spanned::new(evt, span)),
+            Event::End(_) => (),
+            // only span-level tags can be part of math
+            Event::Start(Tag::Emphasis)
+            | Event::Start(Tag::Strong)
+            | Event::Start(Tag::Strikethrough)
+            | Event::Start(Tag::Link(..))
+            | Event::Start(Tag::Image(..)) if span.start >= math.start => {
+                self.emit_math(&math, span);
+                self.open.push(true);
+            },
+            Event::Start(_) => {
+                self.open.push(false);
+                self.buffer.push_back(Spanned::new(evt, span));
+            },
+            _ => self.emit_math(&math, span),
+        }
+    }
+
+    /// Returns the source at the given range of the file.
+    fn source(&self, range: Range<usize>) -> &'a str {
+        &self.source[range.start - self.offset..range.end - self.offset]
+    }
+
+    /// Passes an event outside of any math.
+    fn pass(&mut self, Spanned { value: evt, span }: Spanned<Event<'a>>) {
+        match evt {
+            Event::Start(_) => self.open.push(false),
+            // tags started within math end outside of it
+            Event::End(_) if self.open.pop().unwrap() => return,
+            _ => (),
+        }
+        self.buffer.push_back(Spanned::new(evt, span));
+    }
+
+    fn emit_math(&mut self, math: &Range<usize>, span: Span) {
+        if self.emitted {
+            return;
+        }
+        self.emitted = true;
+        let span = Span { start: math.start, end: math.end, ..span };
+        let evt = if self.source(math.clone()).starts_with("$$") {
+            // the prefixes must be stripped before trimming, as the first line is usually empty
+            let content = match strip_container_prefixes(self.source(math.start + 2..math.end - 2), self.quotes) {
+                Cow::Borrowed(content) => Cow::Borrowed(content.trim_start()),
+                Cow::Owned(content) => Cow::Owned(content.trim_start().to_string()),
+            };
+            Event::DisplayMath(content)
+        } else {
+            let content = self.source(math.start + 1..math.end - 1);
+            Event::InlineMath(strip_container_prefixes(content, self.quotes))
+        };
+        self.buffer.push_back(Spanned::new(evt, span));
+    }
+}
+
+impl<'a> Iterator for DollarMath<'a> {
+    type Item = Spanned<Event<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(evt) = self.buffer.pop_front() {
+                return Some(evt);
+            }
+            let evt = self.inner.next()?;
+            match evt.value {
+                Event::Start(Tag::BlockQuote) => self.quotes += 1,
+                Event::End(Tag::BlockQuote) => self.quotes -= 1,
+                _ => (),
+            }
+            self.handle(evt);
+        }
+    }
+}
+
+/// Removes the `>` of `quotes` block quotes and the indentation of list items from all but the
+/// first line of math, which are part of the markdown source but not of the math.
+fn strip_container_prefixes(math: &str, quotes: usize) -> Cow<'_, str> {
+    if !math.contains('\n') {
+        return Cow::Borrowed(math);
+    }
+    let mut lines = math.split('\n');
+    let mut res = lines.next().unwrap().to_string();
+    for mut line in lines {
+        for _ in 0..quotes {
+            line = line.trim_start();
+            line = line.strip_prefix('>').unwrap_or(line);
+        }
+        res.push('\n');
+        res.push_str(line.trim_start());
+    }
+    Cow::Owned(res)
+}
+
+/// Returns the ranges of all `$…$` and `$$…$$` within `range` of `source`, skipping `code`.
+///
+/// Opening dollars must be followed and closing dollars preceeded by a non-whitespace character.
+/// A closing dollar must not be followed by a digit, such that `$5 and $10` isn't math.
+/// Math can't contain blank lines. Dollars can be escaped with a backslash.
+///
+/// `code` must be sorted and not overlapping.
+fn find_math(source: &str, range: Range<usize>, code: &[Range<usize>]) -> Vec<Range<usize>> {
+    let bytes = source.as_bytes();
+    let is_dollar = |i: usize| {
+        i < range.end && bytes[i] == b'$'
+            && bytes[range.start..i].iter().rev().take_while(|&&b| b == b'\\').count() % 2 == 0
+    };
+    let in_code = |i: usize| {
+        let idx = code.partition_point(|code| code.end <= i);
+        code.get(idx).filter(|code| code.contains(&i)).map(|code| code.end)
+    };
+    // start of the first blank line after `i`, math can't extend beyond it
+    let paragraph_end = |i: usize| {
+        let mut line_start = i;
+        while let Some(newline) = source[line_start..range.end].find('\n') {
+            line_start += newline + 1;
+            let line_end = source[line_start..range.end].find('\n').map_or(range.end, |len| line_start + len);
+            if source[line_start..line_end].trim().is_empty() {
+                return line_start;
+            }
+        }
+        range.end
+    };
+
+    let mut res = Vec::new();
+    let mut i = range.start;
+    while i < range.end {
+        if let Some(end) = in_code(i) {
+            i = end;
+            continue;
+        }
+        if !is_dollar(i) {
+            i += 1;
+            continue;
+        }
+
+        let display = is_dollar(i + 1);
+        let content_start = if display { i + 2 } else { i + 1 };
+        if content_start >= range.end || (!display && (bytes[content_start] as char).is_ascii_whitespace()) {
+            i = content_start;
+            continue;
+        }
+        let mut end = None;
+        let mut j = content_start;
+        let search_end = paragraph_end(content_start);
+        while j < search_end {
+            if in_code(j).is_some() {
+                break;
+            }
+            if display && is_dollar(j) && is_dollar(j + 1) {
+                end = Some(j + 2);
+                break;
+            }
+            if !display && is_dollar(j) && j > content_start
+                && !(bytes[j - 1] as char).is_ascii_whitespace()
+                && !bytes.get(j + 1).map_or(false, u8::is_ascii_digit)
+            {
+                end = Some(j + 1);
+                break;
+            }
+            j += 1;
+        }
+        match end {
+            Some(end) => {
+                res.push(i..end);
+                i = end;
+            },
+            None => i = content_start,
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::{find_math, strip_container_prefixes};
+
+    fn math(s: &str) -> Vec<&str> {
+        find_math(s, 0..s.len(), &[]).into_iter().map(|range| &s[range]).collect()
+    }
+
+    #[test]
+    fn test_inline() {
+        assert_eq!(math("foo $a_1 * b_2*$ bar"), vec!["$a_1 * b_2*$"]);
+        assert_eq!(math("$a$ and $b$"), vec!["$a$", "$b$"]);
+        assert_eq!(math("costs $5 and $10"), Vec::<&str>::new());
+        assert_eq!(math("$ a $"), Vec::<&str>::new());
+        assert_eq!(math(r"\$a$"), Vec::<&str>::new());
+        assert_eq!(math("$a\n\nb$"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(math("$$\nx^2\n$$"), vec!["$$\nx^2\n$$"]);
+        assert_eq!(math("foo $$ x $$ bar"), vec!["$$ x $$"]);
+        assert_eq!(math("$$\nx\n\ny\n$$"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_container_prefixes() {
+        assert_eq!(strip_container_prefixes("x^2", 1), "x^2");
+        assert_eq!(strip_container_prefixes("a\n> + b\n>  > c", 2), "a\n+ b\nc");
+        assert_eq!(strip_container_prefixes("a\n     > b", 0), "a\n> b");
+        assert_eq!(strip_container_prefixes("\n> x^2\n> ", 1), "\nx^2\n");
+    }
+
+    #[test]
+    fn test_code() {
+        let s = "`$a$` $b$";
+        assert_eq!(find_math(s, 0..s.len(), &[0..5]), vec![6..9]);
+    }
+}
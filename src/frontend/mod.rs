@@ -1,8 +1,8 @@
 use std::borrow::Cow;
 use std::collections::VecDeque;
 use std::str::FromStr;
-use std::fs::File;
-use std::io::Write;
+use std::io;
+use std::path::Path;
 use diagnostic::{Span, Spanned};
 
 use lazy_static::lazy_static;
@@ -10,20 +10,44 @@ use pulldown_cmark::{BrokenLink, CowStr, Options as CmarkOptions, Parser as Cmar
 use regex::Regex;
 use itertools::structs::MultiPeek;
 
+mod chart;
+mod code_selection;
+mod code_style;
 mod concat;
 mod convert_cow;
+mod csv;
+mod equation;
 mod event;
+mod exec;
+mod flowchart;
+mod grid_table;
+mod math;
 mod refs;
+mod render;
+mod sequence;
 mod size;
+mod svg;
 mod table_layout;
+mod table_spans;
+mod table_style;
 
+pub use self::chart::ChartSpec;
+pub use self::code_selection::{select_code, CodeSelection};
+pub use self::csv::{csv_table_events, CsvTable};
 pub use self::event::*;
 pub use self::size::*;
 pub use self::refs::LinkType;
 
 use self::concat::Concat;
 use self::convert_cow::{ConvertCow, Event as CmarkEvent, Tag as CmarkTag};
+use self::flowchart::Flowchart;
+use self::grid_table::GridTables;
+use self::math::DollarMath;
 use self::refs::ReferenceParseResult;
+use self::sequence::SequenceDiagram;
+use self::grid_table::GridTable;
+use self::table_spans::TableLayout;
+use crate::cache::{self, CacheKey};
 use crate::config::Config;
 use crate::cskvp::Cskvp;
 use crate::error::{DiagnosticCode, Diagnostics};
@@ -36,7 +60,19 @@ pub struct Frontend<'a> {
     diagnostics: &'a Diagnostics,
     parser: MultiPeek<Concat<'a>>,
     buffer: VecDeque<Spanned<Event<'a>>>,
-    svgbob_index: u64,
+    /// HTML comment spanning multiple html events, which isn't closed yet
+    comment: Option<Spanned<String>>,
+    /// If soft breaks within the current paragraph should be converted to hard breaks
+    hard_breaks: bool,
+    /// Rows of the current table, excluding the head
+    table_rows: VecDeque<TableRow>,
+    /// Cells of the current table, `None` for cells merged into another cell
+    table_cells: VecDeque<Option<TableCell>>,
+    /// Head of the current table
+    table_head: Option<TableHead>,
+    /// Currently open row and cell
+    table_row: Option<TableRow>,
+    table_cell: Option<TableCell>,
 }
 
 impl<'a> Iterator for Frontend<'a> {
@@ -69,8 +105,15 @@ fn broken_link_callback<'a>(broken_link: BrokenLink<'a>) -> Option<(CowStr<'a>,
 
 impl<'a> Frontend<'a> {
     pub fn new(cfg: &'a Config, markdown: Spanned<&'a str>, diagnostics: &'a Diagnostics) -> Frontend<'a> {
-        let parser = CmarkParser::new_with_broken_link_callback(
-            &markdown.value[markdown.span.start..markdown.span.end],
+        Frontend::with_offset(cfg, markdown, 0, diagnostics)
+    }
+
+    /// Like `new`, but `markdown` only contains the file starting at byte `offset`.
+    fn with_offset(
+        cfg: &'a Config, markdown: Spanned<&'a str>, offset: usize, diagnostics: &'a Diagnostics,
+    ) -> Frontend<'a> {
+        let new_parser = || CmarkParser::new_with_broken_link_callback(
+            &markdown.value[markdown.span.start - offset..markdown.span.end - offset],
             CmarkOptions::ENABLE_FOOTNOTES
                 | CmarkOptions::ENABLE_TABLES
                 | CmarkOptions::ENABLE_STRIKETHROUGH
@@ -78,12 +121,20 @@ impl<'a> Frontend<'a> {
             Some(Box::leak(Box::new(broken_link_callback))),
         )
         .into_offset_iter();
+        // the first pass is only used to find code, which must not contain dollar math
+        let parser = DollarMath::new(markdown, offset, ConvertCow(markdown.span, new_parser()), new_parser());
         Frontend {
             cfg,
             diagnostics,
-            parser: itertools::multipeek(Concat::new(ConvertCow(markdown.span, parser))),
+            parser: itertools::multipeek(Concat::new(GridTables::new(markdown.value, offset, parser))),
             buffer: VecDeque::new(),
-            svgbob_index: 0,
+            comment: None,
+            hard_breaks: false,
+            table_rows: VecDeque::new(),
+            table_cells: VecDeque::new(),
+            table_head: None,
+            table_row: None,
+            table_cell: None,
         }
     }
 
@@ -96,10 +147,29 @@ impl<'a> Frontend<'a> {
                     self.convert_inline_code(Spanned::new(cow, span));
                     None
                 },
-                CmarkEvent::Html(html) => Some(self.convert_html(html)),
+                CmarkEvent::Html(html) => {
+                    self.convert_html(Spanned::new(html, span));
+                    None
+                },
+                CmarkEvent::InlineMath(math) => {
+                    self.buffer.push_back(Spanned::new(Event::Start(Tag::InlineMath), span));
+                    self.buffer.push_back(Spanned::new(Event::Text(math), span));
+                    Some(Event::End(Tag::InlineMath))
+                },
+                CmarkEvent::DisplayMath(math) => {
+                    let tag = Tag::Equation(Equation {
+                        label: None,
+                        caption: None,
+                        environment: EquationEnvironment::Align,
+                    });
+                    self.buffer.push_back(Spanned::new(Event::Start(tag.clone()), span));
+                    self.buffer.push_back(Spanned::new(Event::Text(math), span));
+                    Some(Event::End(tag))
+                },
                 CmarkEvent::FootnoteReference(label) => {
                     Some(Event::FootnoteReference(FootnoteReference { label }))
                 },
+                CmarkEvent::SoftBreak if self.hard_breaks => Some(Event::HardBreak),
                 CmarkEvent::SoftBreak => Some(Event::SoftBreak),
                 CmarkEvent::HardBreak => Some(Event::HardBreak),
                 CmarkEvent::Rule => Some(Event::Rule),
@@ -130,12 +200,31 @@ impl<'a> Frontend<'a> {
                 CmarkEvent::End(CmarkTag::FootnoteDefinition(label)) => {
                     Some(Event::End(Tag::FootnoteDefinition(FootnoteDefinition { label })))
                 },
-                CmarkEvent::Start(CmarkTag::TableHead) => Some(Event::Start(Tag::TableHead)),
-                CmarkEvent::End(CmarkTag::TableHead) => Some(Event::End(Tag::TableHead)),
-                CmarkEvent::Start(CmarkTag::TableRow) => Some(Event::Start(Tag::TableRow)),
-                CmarkEvent::End(CmarkTag::TableRow) => Some(Event::End(Tag::TableRow)),
-                CmarkEvent::Start(CmarkTag::TableCell) => Some(Event::Start(Tag::TableCell)),
-                CmarkEvent::End(CmarkTag::TableCell) => Some(Event::End(Tag::TableCell)),
+                CmarkEvent::Start(CmarkTag::TableHead) => Some(Event::Start(Tag::TableHead(self.table_head.clone().unwrap()))),
+                CmarkEvent::End(CmarkTag::TableHead) => Some(Event::End(Tag::TableHead(self.table_head.take().unwrap()))),
+                CmarkEvent::Start(CmarkTag::TableRow) => {
+                    let row = self.table_rows.pop_front().unwrap();
+                    self.table_row = Some(row.clone());
+                    Some(Event::Start(Tag::TableRow(row)))
+                },
+                CmarkEvent::End(CmarkTag::TableRow) => Some(Event::End(Tag::TableRow(self.table_row.take().unwrap()))),
+                CmarkEvent::Start(CmarkTag::TableCell) => match self.table_cells.pop_front().unwrap() {
+                    // merged into another cell
+                    None => {
+                        self.consume_until_end_inclusive();
+                        None
+                    },
+                    Some(cell) if cell.covered => {
+                        self.consume_until_end_inclusive();
+                        self.buffer.push_back(Spanned::new(Event::Start(Tag::TableCell(cell.clone())), span));
+                        Some(Event::End(Tag::TableCell(cell)))
+                    },
+                    Some(cell) => {
+                        self.table_cell = Some(cell.clone());
+                        Some(Event::Start(Tag::TableCell(cell)))
+                    },
+                },
+                CmarkEvent::End(CmarkTag::TableCell) => Some(Event::End(Tag::TableCell(self.table_cell.take().unwrap()))),
                 CmarkEvent::Start(CmarkTag::Emphasis) => Some(Event::Start(Tag::InlineEmphasis)),
                 CmarkEvent::End(CmarkTag::Emphasis) => Some(Event::End(Tag::InlineEmphasis)),
                 CmarkEvent::Start(CmarkTag::Strong) => Some(Event::Start(Tag::InlineStrong)),
@@ -159,6 +248,10 @@ impl<'a> Frontend<'a> {
                     self.convert_table(Spanned::new(alignment, span), None);
                     None
                 },
+                CmarkEvent::GridTable(table) => {
+                    self.convert_grid_table(Spanned::new(table, span), None);
+                    None
+                },
                 CmarkEvent::Start(CmarkTag::Link(typ, dst, title)) => {
                     self.convert_link(typ, dst, title, span);
                     None
@@ -184,11 +277,56 @@ impl<'a> Frontend<'a> {
         }
     }
 
-    fn convert_html(&mut self, html: Cow<'a, str>) -> Event<'a> {
+    fn convert_html(&mut self, Spanned { value: html, span }: Spanned<Cow<'a, str>>) {
+        // multi-line HTML blocks are split into one html event per line
+        if let Some(Spanned { value: comment, span: comment_span }) = &mut self.comment {
+            comment.push_str(&html);
+            comment_span.end = span.end;
+            if html.contains("-->") {
+                let comment = self.comment.take().unwrap();
+                self.convert_comment(comment.map(Cow::Owned));
+            }
+            return;
+        }
+        if html.trim_start().starts_with("<!--") {
+            if html.contains("-->") {
+                self.convert_comment(Spanned::new(html, span));
+            } else {
+                self.comment = Some(Spanned::new(html.into_owned(), span));
+            }
+            return;
+        }
+
         // TODO: proper HTML tag parsing
-        match html.as_ref() {
+        let evt = match html.as_ref() {
             "<br>" | "<br/>" | "<br />" => Event::HardBreak,
             _ => Event::Html(html),
+        };
+        self.buffer.push_back(Spanned::new(evt, span));
+    }
+
+    /// Converts a complete HTML comment `<!-- … -->`, which may be followed by further html.
+    ///
+    /// Normal comments never reach the output. Reviewer notes (`<!-- note: … -->`) are
+    /// converted to comment events if `show_comments` is enabled.
+    fn convert_comment(&mut self, Spanned { value: mut html, span }: Spanned<Cow<'a, str>>) {
+        html.trim_start_inplace();
+        let end = html.find("-->").unwrap() + "-->".len();
+        let mut rest = html.split_off(end);
+        let mut comment = html;
+        comment.truncate_start("<!--".len());
+        comment.truncate_end("-->".len());
+        comment.trim_inplace();
+
+        if self.cfg.show_comments && comment.starts_with_ignore_ascii_case("note:") {
+            comment.truncate_start("note:".len());
+            comment.trim_inplace();
+            self.buffer.push_back(Spanned::new(Event::Comment(comment), span));
+        }
+
+        rest.trim_inplace();
+        if !rest.is_empty() {
+            self.convert_html(Spanned::new(rest, span));
         }
     }
 
@@ -258,6 +396,9 @@ impl<'a> Frontend<'a> {
                 CmarkEvent::Text(text) => s += &text,
                 CmarkEvent::Code(text) => { s += "`"; s += &text; s += "`" },
                 CmarkEvent::Html(html) => s += &html,
+                CmarkEvent::InlineMath(math) => { s += "$"; s += &math; s += "$" },
+                CmarkEvent::DisplayMath(math) => { s += "$$"; s += &math; s += "$$" },
+                CmarkEvent::GridTable(table) => s += &table,
                 CmarkEvent::SoftBreak | CmarkEvent::HardBreak => s += " ",
                 CmarkEvent::Rule => (),
                 CmarkEvent::FootnoteReference(_) => (),
@@ -347,22 +488,128 @@ impl<'a> Frontend<'a> {
 
         let mut cskvp = cskvp.unwrap_or_default();
         let tag = match &*language {
-            "equation" | "$$" => {
-                Tag::Equation(Equation { label: cskvp.take_label(), caption: cskvp.take_caption() })
+            "equation" | "$$" | "numberedequation" | "$$$" => {
+                let numbered = language == "numberedequation" || language == "$$$";
+                let environment = cskvp.take_double("env").and_then(|Spanned { value: env, span }| {
+                    match EquationEnvironment::from_str(&env) {
+                        Ok(env) => Some(env),
+                        Err(_) => {
+                            self.diagnostics
+                                .error(DiagnosticCode::InvalidEquationEnvironment)
+                                .with_error_label(span, "defined here")
+                                .with_note("supported environments are `align`, `gather`, `multline`, `split` and `cases`")
+                                .with_note("using `align`")
+                                .emit();
+                            None
+                        },
+                    }
+                });
+                let environment = environment.unwrap_or(EquationEnvironment::Align);
+                let equation = Equation { label: cskvp.take_label(), caption: cskvp.take_caption(), environment };
+                let tag = if numbered { Tag::NumberedEquation(equation) } else { Tag::Equation(equation) };
+
+                self.buffer.push_back(Spanned::new(Event::Start(tag.clone()), span));
+                let mut text = None;
+                loop {
+                    match self.parser.next().unwrap() {
+                        Spanned { value: CmarkEvent::Text(content), span } => text = Some(Spanned::new(content, span)),
+                        Spanned { value: CmarkEvent::End(CmarkTag::CodeBlock(_)), .. } => break,
+                        _ => unreachable!(),
+                    }
+                }
+                if let Some(text) = text {
+                    let events = equation::convert_equation(text, environment, numbered, self.diagnostics);
+                    self.buffer.extend(events);
+                }
+                self.buffer.push_back(Spanned::new(Event::End(tag), span));
+                return;
             },
-            "numberedequation" | "$$$" => Tag::NumberedEquation(Equation {
-                label: cskvp.take_label(),
-                caption: cskvp.take_caption(),
-            }),
             "graphviz" => {
+                let engine = cskvp.take_double("engine").and_then(|Spanned { value: engine, span }| {
+                    match GraphvizEngine::from_str(&engine) {
+                        Ok(engine) => Some(engine),
+                        Err(_) => {
+                            self.diagnostics
+                                .error(DiagnosticCode::InvalidGraphvizOption)
+                                .with_error_label(span, "unknown layout engine")
+                                .with_note("supported engines are `dot`, `neato`, `circo`, `fdp`, `twopi` and `sfdp`")
+                                .with_note("using `dot`")
+                                .emit();
+                            None
+                        },
+                    }
+                });
+                let format = cskvp.take_double("format").and_then(|Spanned { value: format, span }| {
+                    match GraphvizFormat::from_str(&format) {
+                        Ok(format) => Some(format),
+                        Err(_) => {
+                            self.diagnostics
+                                .error(DiagnosticCode::InvalidGraphvizOption)
+                                .with_error_label(span, "unknown output format")
+                                .with_note("supported formats are `pdf` and `svg`")
+                                .with_note("using `pdf`")
+                                .emit();
+                            None
+                        },
+                    }
+                });
+                let attributes = cskvp.take_double("graph")
+                    .map_or_else(Vec::new, |graph| self.graphviz_attributes(graph));
+                let code_span = self.peek_code_span();
+                let label = cskvp.take_label();
+                let caption = cskvp.take_caption();
+                let scale = cskvp.take_double("scale");
+                let width = cskvp.take_double("width");
+                let height = cskvp.take_double("height");
                 let graphviz = Graphviz {
+                    label,
+                    caption,
+                    scale,
+                    width,
+                    height,
+                    engine: engine.unwrap_or(GraphvizEngine::Dot),
+                    format: format.unwrap_or(GraphvizFormat::Pdf),
+                    attributes,
+                    code_span,
+                };
+                Tag::Graphviz(graphviz)
+            },
+            "gnuplot" => {
+                let terminal = cskvp.take_double("terminal").and_then(|Spanned { value: terminal, span }| {
+                    match GnuplotTerminal::from_str(&terminal) {
+                        Ok(terminal) => Some(terminal),
+                        Err(_) => {
+                            self.diagnostics
+                                .error(DiagnosticCode::InvalidGnuplotTerminal)
+                                .with_error_label(span, "defined here")
+                                .with_note("supported terminals are `cairolatex` and `pdfcairo`")
+                                .with_note("using `cairolatex`")
+                                .emit();
+                            None
+                        },
+                    }
+                });
+                let script_span = self.peek_code_span();
+                let gnuplot = Gnuplot {
                     label: cskvp.take_label(),
                     caption: cskvp.take_caption(),
                     scale: cskvp.take_double("scale"),
                     width: cskvp.take_double("width"),
                     height: cskvp.take_double("height"),
+                    terminal: terminal.unwrap_or(GnuplotTerminal::Cairolatex),
+                    script_span,
                 };
-                Tag::Graphviz(graphviz)
+                Tag::Gnuplot(gnuplot)
+            },
+            "tikz" => {
+                let tikz = Tikz {
+                    label: cskvp.take_label(),
+                    caption: cskvp.take_caption(),
+                    scale: cskvp.take_double("scale"),
+                    width: cskvp.take_double("width"),
+                    height: cskvp.take_double("height"),
+                };
+                Tag::Tikz(tikz)
             },
             "inlinelatex" => {
                 // code is just a single block of text
@@ -381,55 +628,97 @@ impl<'a> Frontend<'a> {
                 return;
             },
             "svgbob" => {
-                let Spanned { value: evt, span: svgbob_span } = self.parser.next().unwrap();
-                let content = match evt {
-                    CmarkEvent::Text(content) => content,
-                    _ => unreachable!(),
+                let Spanned { value: content, span: svgbob_span } = self.code_block_text();
+                let svg = svgbob::to_svg(&content);
+                self.push_svg_include("svgbob", &svg, cskvp, svgbob_span);
+                return;
+            },
+            "sequence" | "flow" => {
+                let Spanned { value: content, span: content_span } = self.code_block_text();
+                let svg = match &*language {
+                    "sequence" => SequenceDiagram::parse(&content).map(|diagram| diagram.to_svg()),
+                    _ => Flowchart::parse(&content).map(|chart| chart.to_svg()),
                 };
-                // consume end tag
-                match self.parser.next().unwrap().value {
-                    CmarkEvent::End(CmarkTag::CodeBlock(_)) => (),
-                    _ => unreachable!(),
+                match svg {
+                    Ok(svg) => self.push_svg_include(&language, &svg, cskvp, content_span),
+                    Err(err) => {
+                        self.invalid_diagram(err, &content, content_span);
+                        cskvp.clear();
+                    },
                 }
-
-                // render svg
-                let filename = format!("svgbob{}.svg", self.svgbob_index);
-                self.svgbob_index += 1;
-                let path = self.cfg.temp_dir.join(filename);
-                let mut file = File::create(&path).expect(&format!("can't create temporary svgbob file {:?}", path));
-                let svg = svgbob::to_svg(&content);
-                writeln!(file, "{}", svg).expect(&format!("can't write to temporary svgbob file {:?}", path));
-
-                self.buffer.push_back(Spanned::new(Event::Include(Include {
-                    resolve_security: ResolveSecurity::SkipChecks,
+                return;
+            },
+            "chart" => {
+                let Spanned { value: content, span: content_span } = self.code_block_text();
+                let spec = match ChartSpec::parse(&content) {
+                    Ok(spec) => spec,
+                    Err(err) => {
+                        self.invalid_diagram(err, &content, content_span);
+                        cskvp.clear();
+                        return;
+                    },
+                };
+                let data_file = spec.data_file().map(|(file, line)| (file.to_string(), line));
+                match data_file {
+                    // the data file is resolved and the chart rendered by the generator
+                    Some((file, line)) => {
+                        let include = image_include(file.into(), ResolveSecurity::Default, &mut cskvp);
+                        let data_span = line_span(&content, content_span, line).unwrap_or(content_span);
+                        let chart = Chart { spec, include, data_span };
+                        self.buffer.push_back(Spanned::new(Event::Chart(chart), content_span));
+                    },
+                    None => match spec.to_svg(spec.inline_data().unwrap(), ',') {
+                        Ok(svg) => self.push_svg_include("chart", &svg, cskvp, content_span),
+                        Err(message) => {
+                            self.diagnostics
+                                .error(DiagnosticCode::InvalidChartData)
+                                .with_error_label(content_span, message)
+                                .with_note("skipping over it")
+                                .emit();
+                            cskvp.clear();
+                        },
+                    },
+                }
+                return;
+            },
+            // built-in languages can't be replaced by renderers
+            language if self.cfg.renderers.contains_key(language) => {
+                self.convert_rendered_code_block(language, cskvp, span);
+                return;
+            },
+            _ => {
+                let code_block = CodeBlock {
                     label: cskvp.take_label(),
                     caption: cskvp.take_caption(),
-                    title: cskvp.take_double("title").map(|Spanned { value: title, .. }| title),
-                    alt_text: cskvp.take_double("alt_text").map(|Spanned { value: title, .. }| title.into()),
-                    dst: format!("file://{}", path.to_unix()
-                        .expect(&format!("non-utf8 path: {:?}", path))).into(),
-                    scale: cskvp.take_double("scale"),
-                    width: cskvp.take_double("width"),
-                    height: cskvp.take_double("height"),
-                }), svgbob_span));
-                return;
+                    basicstyle: cskvp.take_double("basicstyle"),
+                    style: code_style::take_style(&mut cskvp, self.diagnostics),
+                    language: if language.is_empty() {
+                        None
+                    } else {
+                        Some(Spanned::new(language, language_span))
+                    },
+                };
+                // `exec` shows the code and its output, `exec=output` only the output
+                let exec = match (cskvp.take_single("exec"), cskvp.take_double("exec")) {
+                    (Some(Spanned { span, .. }), _) => Some(Spanned::new(true, span)),
+                    (None, Some(Spanned { value, span })) if value == "output" => Some(Spanned::new(false, span)),
+                    (None, Some(Spanned { value, span })) => {
+                        self.diagnostics
+                            .warning(DiagnosticCode::InvalidCodeStyle)
+                            .with_error_label(span, format!("invalid value `{}`", value))
+                            .with_note("use `exec` to show code and output, or `exec=output` to only show the output")
+                            .with_note("showing code and output")
+                            .emit();
+                        Some(Spanned::new(true, span))
+                    },
+                    (None, None) => None,
+                };
+                if let Some(exec) = exec {
+                    self.convert_executed_code_block(code_block, exec, span);
+                    return;
+                }
+                Tag::CodeBlock(code_block)
             },
-            _ => Tag::CodeBlock(CodeBlock {
-                label: cskvp.take_label(),
-                caption: cskvp.take_caption(),
-                basicstyle: cskvp.take_double("basicstyle"),
-                language: if language.is_empty() {
-                    None
-                } else if language == "sequence" {
-                    self.diagnostics
-                        .warning(DiagnosticCode::NotYetImplemented)
-                        .with_error_label(span, "sequence is not yet implemented")
-                        .emit();
-                    None
-                } else {
-                    Some(Spanned::new(language, language_span))
-                },
-            }),
         };
 
         self.buffer.push_back(Spanned::new(Event::Start(tag.clone()), span));
@@ -439,6 +728,244 @@ impl<'a> Frontend<'a> {
         self.buffer.push_back(Spanned::new(Event::End(tag), span));
     }
 
+    /// Returns the span of the code block's text without consuming it, `None` for empty code blocks.
+    fn peek_code_span(&mut self) -> Option<Span> {
+        let span = match self.parser.peek() {
+            Some(Spanned { value: CmarkEvent::Text(_), span }) => Some(*span),
+            _ => None,
+        };
+        self.parser.reset_peek();
+        span
+    }
+
+    /// Parses graph attributes given like `graph="rankdir=LR; bgcolor=lightgray"`.
+    ///
+    /// Invalid attributes are skipped after emitting a diagnostic.
+    fn graphviz_attributes(
+        &self, Spanned { value: graph, span }: Spanned<Cow<'a, str>>,
+    ) -> Vec<(Cow<'a, str>, Cow<'a, str>)> {
+        let mut attributes = Vec::new();
+        for attribute in graph.split(';').map(str::trim).filter(|attribute| !attribute.is_empty()) {
+            match attribute.split_once('=') {
+                Some((key, value)) if !key.trim().is_empty() => {
+                    let (key, value) = (key.trim().to_string(), value.trim().to_string());
+                    attributes.push((key.into(), value.into()));
+                },
+                _ => {
+                    self.diagnostics
+                        .error(DiagnosticCode::InvalidGraphvizOption)
+                        .with_error_label(span, format!("invalid graph attribute `{}`", attribute))
+                        .with_note("graph attributes are given like `graph=\"rankdir=LR; bgcolor=lightgray\"`")
+                        .with_note("skipping over it")
+                        .emit();
+                },
+            }
+        }
+        attributes
+    }
+
+    /// Consumes the text of a code block including its end tag.
+    ///
+    /// The span covers all text of the code block, or is the span of the end tag for empty code blocks.
+    fn code_block_text(&mut self) -> Spanned<String> {
+        let mut text = String::new();
+        let mut text_span = None;
+        loop {
+            let Spanned { value: evt, span } = self.parser.next().unwrap();
+            match evt {
+                CmarkEvent::Text(t) => {
+                    text.push_str(&t);
+                    text_span = Some(text_span.map_or(span, |s: Span| Span { end: span.end, ..s }));
+                },
+                CmarkEvent::End(CmarkTag::CodeBlock(_)) => return Spanned::new(text, text_span.unwrap_or(span)),
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    /// Writes the svg to the cache and includes it with the config of the code block.
+    fn push_svg_include(&mut self, name: &str, svg: &str, mut cskvp: Cskvp<'a>, span: Span) {
+        let path = match cache::write_svg(&self.cfg.cache_dir, name, svg) {
+            Ok(path) => path,
+            Err(e) => {
+                self.diagnostics
+                    .error(DiagnosticCode::ErrorWritingToCache)
+                    .with_error_label(span, format!("can't write this {} to the cache", name))
+                    .with_note(format!("cause: {}", e))
+                    .with_note("skipping over it")
+                    .emit();
+                cskvp.clear();
+                return;
+            },
+        };
+
+        let dst = format!("file://{}", path.to_unix().expect(&format!("non-utf8 path: {:?}", path)));
+        let include = image_include(dst.into(), ResolveSecurity::SkipChecks, &mut cskvp);
+        self.buffer.push_back(Spanned::new(Event::Include(include), span));
+    }
+
+    /// Emits the error of a diagram, which can't be parsed.
+    fn invalid_diagram(&self, svg::ParseError { line, message }: svg::ParseError, content: &str, span: Span) {
+        self.diagnostics
+            .error(DiagnosticCode::InvalidDiagram)
+            .with_error_label(line_span(content, span, line).unwrap_or(span), message)
+            .with_note("skipping over it")
+            .emit();
+    }
+
+    /// Renders a code block with the renderer configured for its language and includes the result.
+    fn convert_rendered_code_block(&mut self, language: &str, mut cskvp: Cskvp<'a>, span: Span) {
+        let code = self.code_block_text().value;
+
+        let cfg = self.cfg;
+        let renderer = &cfg.renderers[language];
+        let key = CacheKey::new("renderer")
+            .with(renderer.command().join("\0"))
+            .with(renderer.output.extension())
+            .with(&code);
+        let path = cache::cached(&cfg.cache_dir, key, |dir| {
+            render::render(renderer, &code, dir, "rendered", &cfg.document_folder)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+        });
+        let path = match path {
+            Ok(path) => path,
+            Err(e) => {
+                self.diagnostics
+                    .error(DiagnosticCode::RendererError)
+                    .with_error_label(span, format!("error rendering this `{}` code block", language))
+                    .with_note(e.to_string())
+                    .with_note("skipping over it")
+                    .emit();
+                cskvp.clear();
+                return;
+            },
+        };
+
+        let dst = format!("file://{}", path.to_unix().expect(&format!("non-utf8 path: {:?}", path)));
+        let include = image_include(dst.into(), ResolveSecurity::SkipChecks, &mut cskvp);
+        self.buffer.push_back(Spanned::new(Event::Include(include), span));
+    }
+
+    /// Executes a code block marked with `exec` and adds its stdout as code block and the images
+    /// it generated as includes after it.
+    fn convert_executed_code_block(
+        &mut self, mut code_block: CodeBlock<'a>, Spanned { value: show_code, span: exec_span }: Spanned<bool>,
+        span: Span,
+    ) {
+        let mut code = String::new();
+        loop {
+            match self.parser.next().unwrap().value {
+                CmarkEvent::Text(text) => code.push_str(&text),
+                CmarkEvent::End(CmarkTag::CodeBlock(_)) => break,
+                _ => unreachable!(),
+            }
+        }
+
+        let executed = self.execute(&code_block, &code, exec_span, span);
+        let basicstyle = code_block.basicstyle.clone();
+        let mut label = None;
+        let mut caption = None;
+        if show_code || executed.is_none() {
+            let tag = Tag::CodeBlock(code_block);
+            self.buffer.push_back(Spanned::new(Event::Start(tag.clone()), span));
+            self.buffer.push_back(Spanned::new(Event::Text(code.into()), span));
+            self.buffer.push_back(Spanned::new(Event::End(tag), span));
+        } else {
+            // label and caption belong to the first element of the output
+            label = code_block.label.take();
+            caption = code_block.caption.take();
+        }
+        let executed = match executed {
+            Some(executed) => executed,
+            None => return,
+        };
+
+        if !executed.stdout.trim().is_empty() {
+            let tag = Tag::CodeBlock(CodeBlock {
+                label: label.take(),
+                caption: caption.take(),
+                language: None,
+                basicstyle,
+                style: CodeStyle { linenos: Some(false), ..CodeStyle::default() },
+            });
+            self.buffer.push_back(Spanned::new(Event::Start(tag.clone()), span));
+            self.buffer.push_back(Spanned::new(Event::Text(executed.stdout.into()), span));
+            self.buffer.push_back(Spanned::new(Event::End(tag), span));
+        }
+        for path in executed.images {
+            self.buffer.push_back(Spanned::new(Event::Include(Include {
+                resolve_security: ResolveSecurity::SkipChecks,
+                label: label.take(),
+                caption: caption.take(),
+                title: None,
+                alt_text: None,
+                dst: format!("file://{}", path.to_unix()
+                    .expect(&format!("non-utf8 path: {:?}", path))).into(),
+                scale: None,
+                width: None,
+                height: None,
+                header: true,
+                align: None,
+                table_style: TableStyle::default(),
+                language: None,
+                lines: None,
+                region: None,
+                dedent: true,
+                code_style: CodeStyle::default(),
+            }), span));
+        }
+    }
+
+    /// Executes the code if allowed, returning `None` after emitting a diagnostic otherwise.
+    fn execute(
+        &self, code_block: &CodeBlock<'a>, code: &str, exec_span: Span, span: Span,
+    ) -> Option<exec::Executed> {
+        if !self.cfg.allow_exec {
+            self.diagnostics
+                .warning(DiagnosticCode::CodeExecutionNotAllowed)
+                .with_error_label(exec_span, "code block marked for execution")
+                .with_note("pass `--allow-exec` to execute code blocks")
+                .with_note("only showing the code")
+                .emit();
+            return None;
+        }
+        let language = match &code_block.language {
+            Some(language) => language,
+            None => {
+                self.diagnostics
+                    .error(DiagnosticCode::UnknownInterpreter)
+                    .with_error_label(exec_span, "code block without language marked for execution")
+                    .with_note("add the language to the code block like ```` ```python,exec ````")
+                    .emit();
+                return None;
+            },
+        };
+        let interpreter = match self.cfg.code.interpreter(&language.value) {
+            Some(interpreter) => interpreter,
+            None => {
+                self.diagnostics
+                    .error(DiagnosticCode::UnknownInterpreter)
+                    .with_error_label(language.span, format!("no interpreter for `{}`", language.value))
+                    .with_note(format!("configure one in `[code.interpreters]`, e.g. `{} = \"command\"`", language.value))
+                    .with_note("only showing the code")
+                    .emit();
+                return None;
+            },
+        };
+        match exec::execute(&self.cfg.cache_dir, &self.cfg.document_folder, &interpreter, code) {
+            Ok(executed) => Some(executed),
+            Err(e) => {
+                self.diagnostics
+                    .error(DiagnosticCode::CodeExecutionError)
+                    .with_error_label(span, "error executing this code block")
+                    .with_note(e)
+                    .with_note("only showing the code")
+                    .emit();
+                None
+            },
+        }
+    }
+
     fn convert_paragraph(&mut self, Spanned { value: (), span }: Spanned<()>) {
         if self.check_convert_pagebreak(span) {
             return;
@@ -518,21 +1045,26 @@ impl<'a> Frontend<'a> {
             Spanned { value: CmarkEvent::Start(CmarkTag::Header(_)), .. }
             | Spanned { value: CmarkEvent::Start(CmarkTag::CodeBlock(_)), .. }
             | Spanned { value: CmarkEvent::Start(CmarkTag::Table(_)), .. }
+            | Spanned { value: CmarkEvent::GridTable(_), .. }
             | Spanned { value: CmarkEvent::Start(CmarkTag::Image(..)), .. } => {
                 let Spanned { value: next_element, span: next_span } = self.parser.next().unwrap();
                 self.handle_cskvp(cskvp, next_element, next_span)
             },
             Spanned { value: evt, span: next_span } => {
-                if !cskvp.has_label() {
+                // `{hardbreaks}` at the start of a paragraph applies to the rest of it
+                if !end_paragraph {
+                    self.hard_breaks = cskvp.take_single("hardbreaks").is_some();
+                }
+                if cskvp.has_label() {
+                    self.buffer
+                        .push_back(Spanned::new(Event::Label(cskvp.take_label().unwrap().value), text_span));
+                } else if !self.hard_breaks {
                     self.diagnostics
                         .error(DiagnosticCode::UnapplicableElementConfig)
                         .with_error_label(cskvp.span(), "found element config here")
                         .with_info_label(*next_span, "but it can't be applied to this element")
                         .with_note(format!("that element is of type {:?}, which doesn't support configs", evt))
                         .emit();
-                } else {
-                    self.buffer
-                        .push_back(Spanned::new(Event::Label(cskvp.take_label().unwrap().value), text_span));
                 }
             },
         }
@@ -545,6 +1077,7 @@ impl<'a> Frontend<'a> {
                     false
                 }
             });
+            self.hard_breaks = false;
             self.buffer.push_back(Spanned::new(Event::End(Tag::Paragraph), span));
         }
     }
@@ -578,19 +1111,24 @@ impl<'a> Frontend<'a> {
     fn handle_cskvp(
         &mut self, mut cskvp: Cskvp<'a>, next_element: CmarkEvent<'a>, next_span: Span,
     ) {
+        let table = match &next_element {
+            CmarkEvent::Start(CmarkTag::Table(_)) | CmarkEvent::GridTable(_) => true,
+            CmarkEvent::Start(CmarkTag::Image(_, dst, _)) => is_table_file(dst),
+            _ => false,
+        };
         // check if we want a figure
         let figure = match cskvp.take_figure().map(|f| f.value).unwrap_or(self.cfg.figures) {
             false => None,
-            true => match &next_element {
-                CmarkEvent::Start(CmarkTag::Table(_)) => Some(Tag::TableFigure(Figure {
-                    caption: cskvp.take_caption(),
-                    label: cskvp.take_label(),
-                })),
-                _ => Some(Tag::Figure(Figure {
-                    caption: cskvp.take_caption(),
-                    label: cskvp.take_label(),
-                })),
-            },
+            // longtables can't float, their caption is written by the table itself
+            true if table && cskvp.has_single("longtable") => None,
+            true if table => Some(Tag::TableFigure(Figure {
+                caption: cskvp.take_caption(),
+                label: cskvp.take_label(),
+            })),
+            true => Some(Tag::Figure(Figure {
+                caption: cskvp.take_caption(),
+                label: cskvp.take_label(),
+            })),
         };
         if let Some(figure) = figure.clone() {
             self.buffer.push_back(Spanned::new(Event::Start(figure), next_span));
@@ -606,6 +1144,9 @@ impl<'a> Frontend<'a> {
             CmarkEvent::Start(CmarkTag::Table(alignment)) => {
                 self.convert_table(Spanned::new(alignment, next_span), Some(cskvp))
             },
+            CmarkEvent::GridTable(table) => {
+                self.convert_grid_table(Spanned::new(table, next_span), Some(cskvp))
+            },
             CmarkEvent::Start(CmarkTag::Image(typ, dst, title)) => {
                 self.convert_image(typ, dst, title, next_span, Some(cskvp))
             },
@@ -702,20 +1243,26 @@ impl<'a> Frontend<'a> {
     ) {
         let mut cskvp = cskvp.unwrap_or_default();
 
-        let mut column_lines = vec![Vec::new(); alignment.len()];
+        let mut grid = Vec::new();
+        let mut cell_spans = Vec::new();
         loop {
             match &self.parser.peek().unwrap().value {
                 &CmarkEvent::Start(CmarkTag::TableHead) | &CmarkEvent::Start(CmarkTag::TableRow) => {
-                    let mut i = 0;
+                    let mut row = Vec::new();
+                    let mut row_spans = Vec::new();
                     loop {
-                        let cell_text = match &self.parser.peek().unwrap().value {
+                        let Spanned { value: evt, span: cell_span } = self.parser.peek().unwrap();
+                        let cell_span = *cell_span;
+                        let cell_text = match evt {
                             &CmarkEvent::Start(CmarkTag::TableCell) => self.concat_until_end_inclusive(false),
                             &CmarkEvent::End(CmarkTag::TableHead) | &CmarkEvent::End(CmarkTag::TableRow) => break,
                             e => unreachable!("We are parsing table cells, but got something other than a cell or row-end: {:?}", e),
                         };
-                        column_lines[i].extend(cell_text.lines().map(str::to_owned));
-                        i += 1;
+                        row.push(cell_text);
+                        row_spans.push(cell_span);
                     }
+                    grid.push(row);
+                    cell_spans.push(row_spans);
                 }
                 &CmarkEvent::End(CmarkTag::TableHead) | &CmarkEvent::End(CmarkTag::TableRow) => (),
                 &CmarkEvent::End(CmarkTag::Table(_)) => break,
@@ -724,19 +1271,131 @@ impl<'a> Frontend<'a> {
         }
         self.parser.reset_peek();
 
-        let widths = table_layout::column_widths(column_lines);
-        assert_eq!(widths.len(), alignment.len());
+        let style = table_style::take_style(&mut cskvp, self.diagnostics);
+        let widths = table_style::widths(style.widths.as_ref(), alignment.len(), self.diagnostics);
+        let TableLayout { columns, cells, rows, invalid } =
+            table_spans::layout_table(&grid, alignment, 1, widths, style.booktabs);
+        for (r, c) in invalid {
+            self.diagnostics
+                .warning(DiagnosticCode::InvalidTableSpan)
+                .with_error_label(cell_spans[r][c], "cells merged into this cell don't form a rectangle")
+                .with_note("merge cells with `<<` (cell to the left) and `^^` (cell above)")
+                .with_note("ignoring the merge markers of this cell")
+                .emit();
+        }
+        self.table_cells.extend(cells.into_iter().flatten());
+        // the head isn't a row
+        self.table_rows.extend(rows.into_iter().skip(1));
+        self.table_head = Some(TableHead { booktabs: style.booktabs, longtable: style.longtable });
 
         let tag = Tag::Table(Table {
             label: cskvp.take_label(),
             caption: cskvp.take_caption(),
-            columns: alignment.into_iter().zip(widths.into_iter().map(|w| ColumnWidthPercent(w))).collect(),
+            columns,
+            style,
         });
         self.buffer.push_back(Spanned::new(Event::Start(tag.clone()), span));
         self.convert_until_end_inclusive(|t| if let CmarkTag::Table(_) = t { true } else { false });
         self.buffer.push_back(Spanned::new(Event::End(tag), span));
     }
 
+    fn convert_grid_table(
+        &mut self, Spanned { value: table, span }: Spanned<Cow<'a, str>>, cskvp: Option<Cskvp<'a>>,
+    ) {
+        let mut cskvp = cskvp.unwrap_or_default();
+        let GridTable { alignment, head_rows, rows: grid, line_offsets } = match grid_table::parse(&table) {
+            Ok(grid) => grid,
+            Err(err) => {
+                self.diagnostics
+                    .warning(DiagnosticCode::InvalidGridTable)
+                    .with_error_label(span, "this looks like a grid table, but it isn't valid")
+                    .with_note(err)
+                    .with_note("rendering it as text")
+                    .emit();
+                cskvp.clear();
+                self.buffer.push_back(Spanned::new(Event::Start(Tag::Paragraph), span));
+                self.buffer.push_back(Spanned::new(Event::Text(table), span));
+                self.buffer.push_back(Spanned::new(Event::End(Tag::Paragraph), span));
+                return;
+            },
+        };
+
+        let style = table_style::take_style(&mut cskvp, self.diagnostics);
+        let widths = table_style::widths(style.widths.as_ref(), alignment.len(), self.diagnostics);
+        let TableLayout { columns, cells, rows, invalid } =
+            table_spans::layout_table(&grid, alignment, head_rows, widths, style.booktabs);
+        if !invalid.is_empty() {
+            self.diagnostics
+                .warning(DiagnosticCode::InvalidTableSpan)
+                .with_error_label(span, "this table contains merged cells not forming a rectangle")
+                .with_note("merge cells with `<<` (cell to the left) and `^^` (cell above)")
+                .with_note("ignoring the merge markers of those cells")
+                .emit();
+        }
+
+        let head = TableHead { booktabs: style.booktabs, longtable: style.longtable };
+        let tag = Tag::Table(Table {
+            label: cskvp.take_label(),
+            caption: cskvp.take_caption(),
+            columns,
+            style,
+        });
+        self.buffer.push_back(Spanned::new(Event::Start(tag.clone()), span));
+        let iter = grid.into_iter().zip(line_offsets).zip(cells).zip(rows).enumerate();
+        for (r, (((texts, line_offsets), cells), row)) in iter {
+            let row_tag = if r < head_rows { Tag::TableHead(head.clone()) } else { Tag::TableRow(row) };
+            self.buffer.push_back(Spanned::new(Event::Start(row_tag.clone()), span));
+            for ((text, line_offsets), cell) in texts.into_iter().zip(line_offsets).zip(cells) {
+                let cell = match cell {
+                    Some(cell) => cell,
+                    None => continue,
+                };
+                self.buffer.push_back(Spanned::new(Event::Start(Tag::TableCell(cell.clone())), span));
+                if !cell.covered && !text.is_empty() {
+                    self.convert_grid_table_cell(&text, &line_offsets, span);
+                }
+                self.buffer.push_back(Spanned::new(Event::End(Tag::TableCell(cell)), span));
+            }
+            self.buffer.push_back(Spanned::new(Event::End(row_tag), span));
+        }
+        self.buffer.push_back(Spanned::new(Event::End(tag), span));
+    }
+
+    /// Converts the markdown content of a grid table cell with its own frontend.
+    ///
+    /// The lines of the cell are placed at their offsets within the table, such that spans point
+    /// into the file of the table. The table borders and other cells between them are replaced by
+    /// tabs, which are ignored at the end of lines.
+    ///
+    /// A cell only containing a single paragraph is unwrapped to be rendered like cells of
+    /// normal tables.
+    fn convert_grid_table_cell(&mut self, text: &str, line_offsets: &[usize], table_span: Span) {
+        let start = line_offsets[0];
+        let end = line_offsets[line_offsets.len() - 1] + text.rsplit('\n').next().unwrap().len();
+        let mut source = vec![b'\t'; end - start];
+        for (i, (line, &offset)) in text.split('\n').zip(line_offsets).enumerate() {
+            let offset = offset - start;
+            if i > 0 {
+                source[offset - 1] = b'\n';
+            }
+            source[offset..offset + line.len()].copy_from_slice(line.as_bytes());
+        }
+        // the events borrow from the source for the rest of the conversion
+        let source: &'a str = Box::leak(String::from_utf8(source).unwrap().into_boxed_str());
+        let span = Span { start: table_span.start + start, end: table_span.start + end, ..table_span };
+        let frontend = Frontend::with_offset(self.cfg, Spanned::new(source, span), span.start, self.diagnostics);
+        let mut events: Vec<_> = frontend.collect();
+        let is_start = |evt: &Spanned<Event<'a>>| if let Event::Start(Tag::Paragraph) = evt.value { true } else { false };
+        let single_paragraph = events.iter().filter(|evt| is_start(evt)).count() == 1
+            && events.first().map_or(false, is_start)
+            && events.last().map_or(false, |evt| if let Event::End(Tag::Paragraph) = evt.value { true } else { false });
+        if single_paragraph {
+            events.pop();
+            events.remove(0);
+        }
+        self.buffer.extend(events);
+    }
+
     fn convert_link(
         &mut self, typ: LinkType, dst: Cow<'a, str>, title: Cow<'a, str>, span: Span,
     ) {
@@ -810,8 +1469,57 @@ impl<'a> Frontend<'a> {
                 scale: cskvp.take_double("scale"),
                 width: cskvp.take_double("width"),
                 height: cskvp.take_double("height"),
+                header: cskvp.take_single("noheader").is_none(),
+                align: cskvp.take_double("align"),
+                table_style: table_style::take_style(&mut cskvp, self.diagnostics),
+                language: cskvp.take_double("language"),
+                lines: cskvp.take_double("lines"),
+                region: cskvp.take_double("region"),
+                dedent: cskvp.take_single("nodedent").is_none(),
+                code_style: code_style::take_style(&mut cskvp, self.diagnostics),
             }),
             span,
         ))
     }
 }
+
+/// Include of an image with label, caption, title, alt_text, scale, width and height of the element config.
+fn image_include<'a>(dst: Cow<'a, str>, resolve_security: ResolveSecurity, cskvp: &mut Cskvp<'a>) -> Include<'a> {
+    Include {
+        resolve_security,
+        label: cskvp.take_label(),
+        caption: cskvp.take_caption(),
+        title: cskvp.take_double("title").map(|Spanned { value: title, .. }| title),
+        alt_text: cskvp.take_double("alt_text").map(|Spanned { value: title, .. }| title.into()),
+        dst,
+        scale: cskvp.take_double("scale"),
+        width: cskvp.take_double("width"),
+        height: cskvp.take_double("height"),
+        header: true,
+        align: None,
+        table_style: TableStyle::default(),
+        language: None,
+        lines: None,
+        region: None,
+        dedent: true,
+        code_style: CodeStyle::default(),
+    }
+}
+
+/// Returns whether the destination of an image is a CSV / TSV file, which is included as table.
+fn is_table_file(dst: &str) -> bool {
+    let path = dst.split(|c| c == '?' || c == '#').next().unwrap_or(dst);
+    let ext = Path::new(path).extension().and_then(|ext| ext.to_str()).map(str::to_lowercase);
+    matches!(ext.as_deref(), Some("csv") | Some("tsv") | Some("tab"))
+}
+
+/// Returns the span of the 0-based line within the text starting at `span`.
+///
+/// Returns `None` if the line can't be mapped to the source, e.g. for indented code blocks whose
+/// text doesn't match the source.
+pub fn line_span(text: &str, span: Span, line: usize) -> Option<Span> {
+    let start: usize = text.split('\n').take(line).map(|l| l.len() + 1).sum();
+    let len = text.split('\n').nth(line)?.trim_end_matches('\r').len();
+    let line_span = Span { file: span.file, start: span.start + start, end: span.start + start + len };
+    if line_span.end > span.end { None } else { Some(line_span) }
+}
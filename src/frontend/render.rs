@@ -0,0 +1,139 @@
+use std::ffi::OsString;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+
+use crate::config::Renderer;
+
+/// Renders the code with the renderer's command, returning the path of the rendered file.
+///
+/// Files are named `<name>.<ext>` within `dir`, the command is run within `working_dir`.
+pub fn render(
+    renderer: &Renderer, code: &str, dir: &Path, name: &str, working_dir: &Path,
+) -> Result<PathBuf, String> {
+    let command = renderer.command();
+    let input_path = dir.join(format!("{}.in", name));
+    let output_path = dir.join(format!("{}.{}", name, renderer.output.extension()));
+    let uses_input = command.iter().any(|arg| arg.contains("{input}"));
+    let uses_output = command.iter().any(|arg| arg.contains("{output}"));
+
+    if uses_input {
+        fs::write(&input_path, code).map_err(|e| format!("can't write code to {:?}: {}", input_path, e))?;
+    }
+    let args = command[1..].iter().map(|arg| replace_paths(arg, &input_path, &output_path));
+    let mut child = Command::new(command[0])
+        .args(args)
+        .current_dir(working_dir)
+        .stdin(if uses_input { Stdio::null() } else { Stdio::piped() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("can't run `{}`: {}", command[0], e))?;
+
+    // write in a separate thread, as the command may block on writing its output before
+    // reading all of its input
+    let writer = child.stdin.take().map(|mut stdin| {
+        let code = code.to_string();
+        thread::spawn(move || stdin.write_all(code.as_bytes()))
+    });
+    let output = child.wait_with_output().map_err(|e| format!("error waiting for `{}`: {}", command[0], e))?;
+    if let Some(writer) = writer {
+        writer.join()
+            .expect("thread writing to renderer panicked")
+            .map_err(|e| format!("can't write code to `{}`: {}", command[0], e))?;
+    }
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("`{}` failed with {}:\n{}", command.join(" "), output.status, stderr.trim_end()));
+    }
+
+    if !uses_output {
+        fs::write(&output_path, &output.stdout)
+            .map_err(|e| format!("can't write rendered file {:?}: {}", output_path, e))?;
+    }
+    if !output_path.is_file() {
+        return Err(format!("`{}` didn't write the rendered file {:?}", command.join(" "), output_path));
+    }
+    Ok(output_path)
+}
+
+/// Replaces `{input}` and `{output}` within the argument by the given paths.
+fn replace_paths(mut arg: &str, input_path: &Path, output_path: &Path) -> OsString {
+    let mut replaced = OsString::new();
+    loop {
+        let next = [("{input}", input_path), ("{output}", output_path)]
+            .iter()
+            .filter_map(|&(placeholder, path)| arg.find(placeholder).map(|i| (i, placeholder, path)))
+            .min_by_key(|&(i, ..)| i);
+        match next {
+            Some((i, placeholder, path)) => {
+                replaced.push(&arg[..i]);
+                replaced.push(path);
+                arg = &arg[i + placeholder.len()..];
+            },
+            None => {
+                replaced.push(arg);
+                return replaced;
+            },
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use std::fs;
+    use std::path::Path;
+
+    use tempdir::TempDir;
+
+    use super::{render, replace_paths};
+    use crate::config::Renderer;
+
+    fn renderer(command: &str) -> Renderer {
+        toml::from_str(&format!("command = {:?}\noutput = \"svg\"", command)).unwrap()
+    }
+
+    #[test]
+    fn test_stdio() {
+        let dir = TempDir::new("heradoc-test").unwrap();
+        let path = render(&renderer("cat"), "<svg/>", dir.path(), "foo", dir.path()).unwrap();
+        assert_eq!(path, dir.path().join("foo.svg"));
+        assert_eq!(fs::read_to_string(path).unwrap(), "<svg/>");
+    }
+
+    #[test]
+    fn test_files() {
+        let dir = TempDir::new("heradoc-test").unwrap();
+        let path = render(&renderer("cp {input} {output}"), "<svg/>", dir.path(), "foo", dir.path()).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "<svg/>");
+    }
+
+    #[test]
+    fn test_command_args() {
+        let dir = TempDir::new("heradoc-test").unwrap();
+        // the script is a single argument containing whitespace
+        let renderer: Renderer = toml::from_str(r#"
+            command = ["sh", "-c", 'cp "$0" "$1"', "{input}", "{output}"]
+            output = "svg"
+        "#).unwrap();
+        let path = render(&renderer, "<svg/>", dir.path(), "foo", dir.path()).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "<svg/>");
+    }
+
+    #[test]
+    fn test_replace_paths() {
+        let (input, output) = (Path::new("in.txt"), Path::new("out.svg"));
+        assert_eq!(replace_paths("-o{output}", input, output), "-oout.svg");
+        assert_eq!(replace_paths("{input}:{output}:{input}", input, output), "in.txt:out.svg:in.txt");
+        assert_eq!(replace_paths("-tsvg", input, output), "-tsvg");
+    }
+
+    #[test]
+    fn test_failure() {
+        let dir = TempDir::new("heradoc-test").unwrap();
+        assert!(render(&renderer("false"), "", dir.path(), "foo", dir.path()).is_err());
+        assert!(render(&renderer("true {output}"), "", dir.path(), "foo", dir.path()).is_err());
+    }
+}
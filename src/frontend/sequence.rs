@@ -0,0 +1,322 @@
+//! Sequence diagrams in the syntax of js-sequence-diagrams, rendered to SVG.
+//!
+//! ```text
+//! Title: Login
+//! participant Alice
+//! participant "Authentication Server" as Auth
+//! Alice->Auth: Credentials
+//! Note right of Auth: Check password
+//! Auth-->Alice: Token
+//! ```
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::svg::{text_height, text_width, unescape, Anchor, ParseError, SvgBuilder, LINE_HEIGHT};
+
+/// Padding between the border of a box and its text
+const PADDING: f64 = 8.0;
+/// Minimum horizontal space between the boxes of participants
+const PARTICIPANT_GAP: f64 = 20.0;
+/// Vertical space between messages and notes
+const ROW_GAP: f64 = 12.0;
+/// Width and height of the loop of a message of a participant to itself
+const SELF_WIDTH: f64 = 30.0;
+const SELF_HEIGHT: f64 = 20.0;
+
+#[derive(Debug, PartialEq)]
+pub struct SequenceDiagram {
+    title: Option<String>,
+    participants: Vec<Participant>,
+    items: Vec<Item>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Participant {
+    /// Name used in messages and notes
+    alias: String,
+    /// Displayed name
+    name: String,
+}
+
+#[derive(Debug, PartialEq)]
+enum Item {
+    Message { from: usize, to: usize, text: String, dashed: bool, open: bool },
+    Note { placement: Placement, text: String },
+}
+
+#[derive(Debug, PartialEq)]
+enum Placement {
+    LeftOf(usize),
+    RightOf(usize),
+    Over(usize, usize),
+}
+
+impl SequenceDiagram {
+    pub fn parse(src: &str) -> Result<SequenceDiagram, ParseError> {
+        lazy_static! {
+            static ref TITLE: Regex = Regex::new(r"(?i)^title\s*:\s*(.*)$").unwrap();
+            static ref PARTICIPANT: Regex =
+                Regex::new(r#"(?i)^participant\s+(?:"([^"]*)"|(.+?))(?:\s+as\s+(\S+))?$"#).unwrap();
+            static ref NOTE: Regex =
+                Regex::new(r"(?i)^note\s+(left\s+of|right\s+of|over)\s+([^:]+?)\s*:\s*(.*)$").unwrap();
+            static ref MESSAGE: Regex = Regex::new(r"^(.+?)\s*(-->>|->>|-->|->)\s*([^:]+?)\s*(?::\s*(.*))?$").unwrap();
+        }
+
+        let mut diagram = SequenceDiagram { title: None, participants: Vec::new(), items: Vec::new() };
+        for (i, line) in src.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(captures) = TITLE.captures(line) {
+                if diagram.title.is_some() {
+                    return Err(ParseError { line: i, message: "title defined multiple times".to_string() });
+                }
+                diagram.title = Some(unescape(&captures[1]));
+            } else if let Some(captures) = PARTICIPANT.captures(line) {
+                let name = captures.get(1).or(captures.get(2)).unwrap().as_str();
+                let alias = captures.get(3).map_or(name, |alias| alias.as_str());
+                if diagram.participants.iter().any(|p| p.alias == alias) {
+                    let message = format!("participant `{}` defined multiple times", alias);
+                    return Err(ParseError { line: i, message });
+                }
+                diagram.participants.push(Participant { alias: alias.to_string(), name: unescape(name) });
+            } else if let Some(captures) = NOTE.captures(line) {
+                let mut actors = captures[2].split(',').map(|actor| diagram.participant(actor.trim()));
+                let first = actors.next().unwrap();
+                let second = actors.next();
+                if actors.next().is_some() {
+                    return Err(ParseError { line: i, message: "notes can span at most two participants".to_string() });
+                }
+                let placement = match (captures[1].to_lowercase().split_whitespace().next().unwrap(), second) {
+                    ("over", None) => Placement::Over(first, first),
+                    ("over", Some(second)) => Placement::Over(first.min(second), first.max(second)),
+                    (_, Some(_)) => {
+                        let message = "only notes `over` can span two participants".to_string();
+                        return Err(ParseError { line: i, message });
+                    },
+                    ("left", None) => Placement::LeftOf(first),
+                    (_, None) => Placement::RightOf(first),
+                };
+                diagram.items.push(Item::Note { placement, text: unescape(&captures[3]) });
+            } else if let Some(captures) = MESSAGE.captures(line) {
+                let from = diagram.participant(&captures[1]);
+                let to = diagram.participant(&captures[3]);
+                let arrow = &captures[2];
+                let text = captures.get(4).map_or(String::new(), |text| unescape(text.as_str()));
+                let dashed = arrow.starts_with("--");
+                let open = arrow.ends_with(">>");
+                diagram.items.push(Item::Message { from, to, text, dashed, open });
+            } else {
+                let message = "expected a message like `A->B: text`, a note, a participant or a title".to_string();
+                return Err(ParseError { line: i, message });
+            }
+        }
+        if diagram.participants.is_empty() {
+            return Err(ParseError { line: 0, message: "diagram doesn't contain any participants".to_string() });
+        }
+        Ok(diagram)
+    }
+
+    /// Returns the index of the participant with the given alias, adding it if it doesn't exist.
+    fn participant(&mut self, alias: &str) -> usize {
+        match self.participants.iter().position(|p| p.alias == alias) {
+            Some(i) => i,
+            None => {
+                self.participants.push(Participant { alias: alias.to_string(), name: alias.to_string() });
+                self.participants.len() - 1
+            },
+        }
+    }
+
+    /// Horizontal centers of the participants, such that all messages and notes fit.
+    fn layout_participants(&self) -> Vec<f64> {
+        let widths: Vec<f64> = self.participants.iter()
+            .map(|p| text_width(&p.name) + 2.0 * PADDING)
+            .collect();
+        // minimum distances (left, right, distance) with left < right
+        let mut constraints = Vec::new();
+        let last = self.participants.len() - 1;
+        for item in &self.items {
+            match item {
+                Item::Message { from, to, text, .. } if from == to => {
+                    if *from < last {
+                        constraints.push((*from, from + 1, SELF_WIDTH + text_width(text) + 2.0 * PADDING));
+                    }
+                },
+                Item::Message { from, to, text, .. } => {
+                    constraints.push((*from.min(to), *from.max(to), text_width(text) + 2.0 * PADDING));
+                },
+                Item::Note { placement, text } => {
+                    let width = text_width(text) + 4.0 * PADDING;
+                    match *placement {
+                        Placement::LeftOf(i) if i > 0 => constraints.push((i - 1, i, width)),
+                        Placement::RightOf(i) if i < last => constraints.push((i, i + 1, width)),
+                        Placement::Over(l, r) if l == r => {
+                            if l > 0 {
+                                constraints.push((l - 1, l, width / 2.0 + PADDING));
+                            }
+                            if l < last {
+                                constraints.push((l, l + 1, width / 2.0 + PADDING));
+                            }
+                        },
+                        _ => (),
+                    }
+                },
+            }
+        }
+
+        let mut xs: Vec<f64> = Vec::with_capacity(widths.len());
+        for (i, width) in widths.iter().enumerate() {
+            let mut x = match i {
+                0 => width / 2.0,
+                _ => xs[i - 1] + (widths[i - 1] + width) / 2.0 + PARTICIPANT_GAP,
+            };
+            for &(left, right, distance) in &constraints {
+                if right == i {
+                    x = x.max(xs[left] + distance);
+                }
+            }
+            xs.push(x);
+        }
+        xs
+    }
+
+    pub fn to_svg(&self) -> String {
+        let mut svg = SvgBuilder::new();
+        let xs = self.layout_participants();
+        let mut y = 0.0;
+
+        if let Some(title) = &self.title {
+            let center = (xs[0] + xs[xs.len() - 1]) / 2.0;
+            svg.text(center, y, title, Anchor::Middle);
+            y += text_height(title) + 2.0 * ROW_GAP;
+        }
+
+        let box_height = self.participants.iter()
+            .map(|p| text_height(&p.name))
+            .fold(0.0, f64::max) + 2.0 * PADDING;
+        let participants_top = y;
+        y += box_height + 2.0 * ROW_GAP;
+
+        // messages and notes are drawn after the lifelines, such that they are on top of them
+        let mut elements = SvgBuilder::new();
+        for item in &self.items {
+            match item {
+                Item::Message { from, to, text, dashed, open } => {
+                    let attrs = arrow_attrs(*dashed, *open);
+                    let (from, to) = (xs[*from], xs[*to]);
+                    if from == to {
+                        elements.text(from + SELF_WIDTH + PADDING, y, text, Anchor::Start);
+                        let top = y + LINE_HEIGHT / 2.0;
+                        let points = [
+                            (from, top),
+                            (from + SELF_WIDTH, top),
+                            (from + SELF_WIDTH, top + SELF_HEIGHT),
+                            (from, top + SELF_HEIGHT),
+                        ];
+                        elements.polyline(&points, &attrs);
+                        y += text_height(text).max(LINE_HEIGHT / 2.0 + SELF_HEIGHT) + ROW_GAP;
+                    } else {
+                        if !text.is_empty() {
+                            elements.text((from + to) / 2.0, y, text, Anchor::Middle);
+                            y += text_height(text);
+                        }
+                        y += PADDING / 2.0;
+                        elements.polyline(&[(from, y), (to, y)], &attrs);
+                        y += ROW_GAP;
+                    }
+                },
+                Item::Note { placement, text } => {
+                    let width = text_width(text) + 2.0 * PADDING;
+                    let height = text_height(text) + 2.0 * PADDING;
+                    let (left, width) = match *placement {
+                        Placement::LeftOf(i) => (xs[i] - PADDING - width, width),
+                        Placement::RightOf(i) => (xs[i] + PADDING, width),
+                        Placement::Over(l, r) => {
+                            let (left, right) = (xs[l] - 2.0 * PADDING, xs[r] + 2.0 * PADDING);
+                            let width = width.max(right - left);
+                            ((left + right - width) / 2.0, width)
+                        },
+                    };
+                    elements.rect(left, y, width, height, "lightyellow");
+                    elements.text(left + width / 2.0, y + PADDING, text, Anchor::Middle);
+                    y += height + ROW_GAP;
+                },
+            }
+        }
+        y += ROW_GAP;
+
+        for (participant, &x) in self.participants.iter().zip(&xs) {
+            svg.polyline(&[(x, participants_top + box_height), (x, y)], "");
+            let width = text_width(&participant.name) + 2.0 * PADDING;
+            for &top in &[participants_top, y] {
+                svg.rect(x - width / 2.0, top, width, box_height, "white");
+                let text_top = top + (box_height - text_height(&participant.name)) / 2.0;
+                svg.text(x, text_top, &participant.name, Anchor::Middle);
+            }
+        }
+        svg.append(elements);
+        svg.finish()
+    }
+}
+
+fn arrow_attrs(dashed: bool, open: bool) -> String {
+    let marker = if open { "open-arrow" } else { "arrow" };
+    let dash = if dashed { r#" stroke-dasharray="6,4""# } else { "" };
+    format!(r#"marker-end="url(#{})"{}"#, marker, dash)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn participants(names: &[&str]) -> Vec<Participant> {
+        names.iter().map(|name| Participant { alias: name.to_string(), name: name.to_string() }).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let diagram = SequenceDiagram::parse("Title: Foo\nAlice->Bob: Hello\\nWorld\n\nBob-->>Alice: Hi\nBob->Bob").unwrap();
+        assert_eq!(diagram, SequenceDiagram {
+            title: Some("Foo".to_string()),
+            participants: participants(&["Alice", "Bob"]),
+            items: vec![
+                Item::Message { from: 0, to: 1, text: "Hello\nWorld".to_string(), dashed: false, open: false },
+                Item::Message { from: 1, to: 0, text: "Hi".to_string(), dashed: true, open: true },
+                Item::Message { from: 1, to: 1, text: String::new(), dashed: false, open: false },
+            ],
+        });
+    }
+
+    #[test]
+    fn test_parse_participants_and_notes() {
+        let src = "participant \"Long Name\" as L\nparticipant B\nNote over B,L: both\nnote left of C: c";
+        let diagram = SequenceDiagram::parse(src).unwrap();
+        assert_eq!(diagram.participants[0], Participant { alias: "L".to_string(), name: "Long Name".to_string() });
+        assert_eq!(diagram.participants.len(), 3);
+        assert_eq!(diagram.items, vec![
+            Item::Note { placement: Placement::Over(0, 1), text: "both".to_string() },
+            Item::Note { placement: Placement::LeftOf(2), text: "c".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(SequenceDiagram::parse("A->B: x\nfoo").unwrap_err().line, 1);
+        assert_eq!(SequenceDiagram::parse("Note right of A,B: x").unwrap_err().line, 0);
+        assert!(SequenceDiagram::parse("").is_err());
+    }
+
+    #[test]
+    fn test_layout() {
+        let diagram = SequenceDiagram::parse("A->B: a long message text\nB->C: x").unwrap();
+        let xs = diagram.layout_participants();
+        assert!(xs[1] - xs[0] >= text_width("a long message text"));
+        assert!(xs[2] > xs[1]);
+        let svg = diagram.to_svg();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains(">a long message text</text>"));
+    }
+}
@@ -0,0 +1,179 @@
+use std::fmt::Write;
+
+/// Font size of text in diagrams
+const FONT_SIZE: f64 = 14.0;
+/// Estimated average width of a character, as the actual font isn't known while laying out
+const CHAR_WIDTH: f64 = 8.0;
+pub const LINE_HEIGHT: f64 = 18.0;
+/// Space around the content of the diagram
+const MARGIN: f64 = 10.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+    Start,
+    Middle,
+    End,
+}
+
+/// Estimated width of multi-line text.
+pub fn text_width(text: &str) -> f64 {
+    text.lines().map(|line| line.chars().count()).max().unwrap_or(0) as f64 * CHAR_WIDTH
+}
+
+/// Height of multi-line text.
+pub fn text_height(text: &str) -> f64 {
+    text.lines().count().max(1) as f64 * LINE_HEIGHT
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Error while parsing a diagram
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    /// 0-based line of the error
+    pub line: usize,
+    pub message: String,
+}
+
+/// Replaces the escape sequence `\n` with a line break.
+pub fn unescape(text: &str) -> String {
+    text.trim().replace("\\n", "\n")
+}
+
+/// Builder of SVG images, whose size is determined by the bounding box of all elements.
+#[derive(Debug)]
+pub struct SvgBuilder {
+    elements: String,
+    min: (f64, f64),
+    max: (f64, f64),
+}
+
+impl SvgBuilder {
+    pub fn new() -> SvgBuilder {
+        SvgBuilder {
+            elements: String::new(),
+            min: (f64::INFINITY, f64::INFINITY),
+            max: (f64::NEG_INFINITY, f64::NEG_INFINITY),
+        }
+    }
+
+    fn extend_bounds(&mut self, x: f64, y: f64) {
+        self.min = (self.min.0.min(x), self.min.1.min(y));
+        self.max = (self.max.0.max(x), self.max.1.max(y));
+    }
+
+    pub fn rect(&mut self, x: f64, y: f64, width: f64, height: f64, fill: &str) {
+        self.rounded_rect(x, y, width, height, 0.0, fill);
+    }
+
+    pub fn rounded_rect(&mut self, x: f64, y: f64, width: f64, height: f64, radius: f64, fill: &str) {
+        self.extend_bounds(x, y);
+        self.extend_bounds(x + width, y + height);
+        writeln!(
+            self.elements,
+            r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" rx="{:.1}" fill="{}" stroke="black"/>"#,
+            x, y, width, height, radius, fill,
+        ).unwrap();
+    }
+
+    /// Open line through all points.
+    pub fn polyline(&mut self, points: &[(f64, f64)], attrs: &str) {
+        self.points("polyline", points, "none", "black", attrs);
+    }
+
+    /// Open line through all points drawn in the given color.
+    pub fn colored_polyline(&mut self, points: &[(f64, f64)], stroke: &str, attrs: &str) {
+        self.points("polyline", points, "none", stroke, attrs);
+    }
+
+    /// Closed shape through all points.
+    pub fn polygon(&mut self, points: &[(f64, f64)], attrs: &str) {
+        self.points("polygon", points, "white", "black", attrs);
+    }
+
+    fn points(&mut self, element: &str, points: &[(f64, f64)], fill: &str, stroke: &str, attrs: &str) {
+        let mut list = String::new();
+        for &(x, y) in points {
+            self.extend_bounds(x, y);
+            write!(list, "{:.1},{:.1} ", x, y).unwrap();
+        }
+        writeln!(
+            self.elements,
+            r#"<{} points="{}" fill="{}" stroke="{}" {}/>"#,
+            element, list.trim_end(), fill, stroke, attrs,
+        ).unwrap();
+    }
+
+    pub fn circle(&mut self, x: f64, y: f64, radius: f64, fill: &str) {
+        self.extend_bounds(x - radius, y - radius);
+        self.extend_bounds(x + radius, y + radius);
+        writeln!(
+            self.elements,
+            r#"<circle cx="{:.1}" cy="{:.1}" r="{:.1}" fill="{}" stroke="black"/>"#,
+            x, y, radius, fill,
+        ).unwrap();
+    }
+
+    /// Multi-line text, whose first line starts at `top`.
+    pub fn text(&mut self, x: f64, top: f64, text: &str, anchor: Anchor) {
+        let width = text_width(text);
+        let left = match anchor {
+            Anchor::Start => x,
+            Anchor::Middle => x - width / 2.0,
+            Anchor::End => x - width,
+        };
+        self.extend_bounds(left, top);
+        self.extend_bounds(left + width, top + text_height(text));
+        let anchor = match anchor {
+            Anchor::Start => "start",
+            Anchor::Middle => "middle",
+            Anchor::End => "end",
+        };
+        for (i, line) in text.lines().enumerate() {
+            // baseline of the line, leaving space for descenders
+            let y = top + (i + 1) as f64 * LINE_HEIGHT - 4.0;
+            writeln!(
+                self.elements,
+                r#"<text x="{:.1}" y="{:.1}" font-family="sans-serif" font-size="{}" text-anchor="{}">{}</text>"#,
+                x, y, FONT_SIZE, anchor, escape(line),
+            ).unwrap();
+        }
+    }
+
+    /// Adds all elements of the other builder on top of the elements of this one.
+    pub fn append(&mut self, other: SvgBuilder) {
+        if !other.elements.is_empty() {
+            self.extend_bounds(other.min.0, other.min.1);
+            self.extend_bounds(other.max.0, other.max.1);
+            self.elements.push_str(&other.elements);
+        }
+    }
+
+    /// Returns the SVG document, defining the markers `arrow` and `open-arrow` for line ends.
+    pub fn finish(mut self) -> String {
+        if self.elements.is_empty() {
+            self.extend_bounds(0.0, 0.0);
+        }
+        let (min_x, min_y) = (self.min.0 - MARGIN, self.min.1 - MARGIN);
+        let (width, height) = (self.max.0 - self.min.0 + 2.0 * MARGIN, self.max.1 - self.min.1 + 2.0 * MARGIN);
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w:.1}" height="{h:.1}" viewBox="{:.1} {:.1} {w:.1} {h:.1}">"#,
+            min_x, min_y, w = width, h = height,
+        ).unwrap();
+        svg.push_str(concat!(
+            "<defs>\n",
+            r#"<marker id="arrow" viewBox="0 0 10 10" refX="10" refY="5" markerWidth="10" markerHeight="10" markerUnits="userSpaceOnUse" orient="auto">"#,
+            r#"<path d="M0,0 L10,5 L0,10 z" fill="black"/></marker>"#, "\n",
+            r#"<marker id="open-arrow" viewBox="0 0 10 10" refX="10" refY="5" markerWidth="10" markerHeight="10" markerUnits="userSpaceOnUse" orient="auto">"#,
+            r#"<path d="M0,0 L10,5 L0,10" fill="none" stroke="black"/></marker>"#, "\n",
+            "</defs>\n",
+        ));
+        svg.push_str(&self.elements);
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
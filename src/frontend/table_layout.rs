@@ -16,7 +16,7 @@ struct Column {
     calc: f32,
 }
 
-fn text_width(line: &str) -> f32 {
+pub fn text_width(line: &str) -> f32 {
     line.chars().map(|c| {
         if NARROWS.contains(c) {
             0.4
@@ -28,19 +28,23 @@ fn text_width(line: &str) -> f32 {
     }).sum::<f32>().max(1.0)
 }
 
-pub fn column_widths(column_lines: Vec<Vec<String>>) -> Vec<f32> {
+/// Calculates the width of each column in percent from the text widths of its lines.
+pub fn column_widths(column_lines: Vec<Vec<f32>>) -> Vec<f32> {
     let min_percentage = 100.0 / 3.0 / column_lines.len() as f32;
 
     let mut columns = Vec::new();
 
-    for column in column_lines {
+    for mut column in column_lines {
+        // all cells of this column are part of cells spanning multiple columns
+        if column.is_empty() {
+            column.push(1.0);
+        }
         let mut max = 0.0f32;
         let mut sum = 0.0;
         let mut count = 0;
         let mut widths = Vec::new();
 
-        for line in column {
-            let width = text_width(&line);
+        for width in column {
             max = max.max(width);
             sum += width;
             count += 1;
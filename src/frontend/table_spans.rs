@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+
+use pulldown_cmark::Alignment;
+
+use super::event::{ColumnWidthPercent, TableCell, TableRow};
+use super::table_layout;
+
+/// Layout of a single cell of a table after resolving `<<` and `^^` marker cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellLayout {
+    /// Cell with content spanning the given number of columns and rows
+    Cell { colspan: usize, rowspan: usize },
+    /// First column of a cell spanning multiple rows in a row below its content
+    Covered { colspan: usize },
+    /// Cell merged into another one, which must not be emitted
+    Skip,
+}
+
+/// Resolves the spans of cells.
+///
+/// A cell only containing `<<` is merged with the cell to its left, a cell only containing `^^`
+/// is merged with the cell above it. Cells can't span from the header rows into the body.
+///
+/// Returns the layout of each cell and a list of cells (row, column), whose merged cells don't
+/// form a rectangle. Those cells are not merged and their markers are kept as text.
+pub fn resolve_spans(grid: &[Vec<String>], head_rows: usize) -> (Vec<Vec<CellLayout>>, Vec<(usize, usize)>) {
+    let mut owners: Vec<Vec<(usize, usize)>> = Vec::with_capacity(grid.len());
+    for (r, row) in grid.iter().enumerate() {
+        let mut owner_row: Vec<(usize, usize)> = Vec::with_capacity(row.len());
+        for (c, text) in row.iter().enumerate() {
+            let owner = match text.trim() {
+                "<<" if c > 0 => owner_row[c - 1],
+                "^^" if r > head_rows || (r > 0 && r < head_rows) => owners[r - 1][c],
+                _ => (r, c),
+            };
+            owner_row.push(owner);
+        }
+        owners.push(owner_row);
+    }
+
+    // bounding box (min_row, max_row, min_col, max_col) and number of cells of every owner
+    let mut boxes: HashMap<(usize, usize), (usize, usize, usize, usize, usize)> = HashMap::new();
+    for (r, row) in owners.iter().enumerate() {
+        for (c, &owner) in row.iter().enumerate() {
+            let b = boxes.entry(owner).or_insert((r, r, c, c, 0));
+            b.0 = b.0.min(r);
+            b.1 = b.1.max(r);
+            b.2 = b.2.min(c);
+            b.3 = b.3.max(c);
+            b.4 += 1;
+        }
+    }
+
+    let mut invalid = Vec::new();
+    for (&owner, &(min_row, max_row, min_col, max_col, count)) in &boxes {
+        let rectangular = owner == (min_row, min_col)
+            && count == (max_row - min_row + 1) * (max_col - min_col + 1);
+        if !rectangular {
+            invalid.push(owner);
+        }
+    }
+    invalid.sort();
+    // merged cells of invalid spans are kept as they are
+    for (r, row) in owners.iter_mut().enumerate() {
+        for (c, owner) in row.iter_mut().enumerate() {
+            if invalid.contains(owner) {
+                *owner = (r, c);
+            }
+        }
+    }
+
+    let layout = owners
+        .iter()
+        .enumerate()
+        .map(|(r, row)| {
+            row.iter()
+                .enumerate()
+                .map(|(c, &owner)| {
+                    if owner == (r, c) {
+                        match boxes.get(&owner) {
+                            Some(&(min_row, max_row, min_col, max_col, _)) if !invalid.contains(&owner) => {
+                                debug_assert_eq!((min_row, min_col), (r, c));
+                                CellLayout::Cell { colspan: max_col - min_col + 1, rowspan: max_row - min_row + 1 }
+                            },
+                            _ => CellLayout::Cell { colspan: 1, rowspan: 1 },
+                        }
+                    } else if c == owner.1 {
+                        let (_, _, min_col, max_col, _) = boxes[&owner];
+                        CellLayout::Covered { colspan: max_col - min_col + 1 }
+                    } else {
+                        CellLayout::Skip
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    (layout, invalid)
+}
+
+/// Returns for each cell if it needs a horizontal rule below it.
+///
+/// Cells spanning multiple rows only have a rule below their last row.
+pub fn rules_below(layout: &[Vec<CellLayout>]) -> Vec<Vec<bool>> {
+    let columns = layout.first().map_or(0, Vec::len);
+    // number of rows the cell in each column continues after the current row
+    let mut remaining = vec![0; columns];
+    layout
+        .iter()
+        .map(|row| {
+            for (c, cell) in row.iter().enumerate() {
+                match *cell {
+                    CellLayout::Cell { colspan, rowspan } => {
+                        remaining[c..c + colspan].iter_mut().for_each(|rem| *rem = rowspan - 1)
+                    },
+                    CellLayout::Covered { colspan } => {
+                        remaining[c..c + colspan].iter_mut().for_each(|rem| *rem -= 1)
+                    },
+                    CellLayout::Skip => (),
+                }
+            }
+            remaining.iter().map(|&rem| rem == 0).collect()
+        })
+        .collect()
+}
+
+/// Columns, cells and rows of a table with merged cells.
+pub struct TableLayout {
+    pub columns: Vec<(Alignment, ColumnWidthPercent)>,
+    /// Cells of each row, `None` for cells merged into another one
+    pub cells: Vec<Vec<Option<TableCell>>>,
+    /// Every row of the grid, including the header rows
+    pub rows: Vec<TableRow>,
+    /// Cells whose merged cells don't form a rectangle, see [`resolve_spans`]
+    pub invalid: Vec<(usize, usize)>,
+}
+
+/// Resolves the merged cells of the given grid and calculates the column widths if no explicit
+/// `widths` in percent are given.
+///
+/// `alignment` must contain an entry for each column of the grid. With `booktabs` there aren't
+/// any rules between rows.
+pub fn layout_table(
+    grid: &[Vec<String>], alignment: Vec<Alignment>, head_rows: usize, widths: Option<Vec<f32>>,
+    booktabs: bool,
+) -> TableLayout {
+    let (layout, invalid) = resolve_spans(grid, head_rows);
+
+    // cells spanning multiple columns contribute to each of them equally
+    let mut column_lines = vec![Vec::new(); alignment.len()];
+    for (row, layout_row) in grid.iter().zip(&layout) {
+        for (c, (text, cell)) in row.iter().zip(layout_row).enumerate() {
+            if let CellLayout::Cell { colspan, .. } = *cell {
+                for line in text.lines() {
+                    let width = table_layout::text_width(line) / colspan as f32;
+                    for lines in &mut column_lines[c..c + colspan] {
+                        lines.push(width);
+                    }
+                }
+            }
+        }
+    }
+    let widths = widths.unwrap_or_else(|| table_layout::column_widths(column_lines));
+    assert_eq!(widths.len(), alignment.len());
+    let columns: Vec<_> = alignment.into_iter().zip(widths.into_iter().map(|w| ColumnWidthPercent(w))).collect();
+
+    let cells = layout
+        .iter()
+        .map(|layout_row| {
+            layout_row
+                .iter()
+                .enumerate()
+                .map(|(c, cell)| {
+                    let (colspan, rowspan, covered) = match *cell {
+                        CellLayout::Cell { colspan, rowspan } => (colspan, rowspan, false),
+                        CellLayout::Covered { colspan } => (colspan, 1, true),
+                        CellLayout::Skip => return None,
+                    };
+                    let width = columns[c..c + colspan].iter().map(|(_, width)| width.0).sum();
+                    Some(TableCell {
+                        column: c,
+                        colspan,
+                        rowspan,
+                        covered,
+                        alignment: columns[c].0,
+                        width: ColumnWidthPercent(width),
+                        booktabs,
+                    })
+                })
+                .collect()
+        })
+        .collect();
+    let rows = rules_below(&layout)
+        .into_iter()
+        .map(|rules_below| if booktabs { vec![false; rules_below.len()] } else { rules_below })
+        .map(|rules_below| TableRow { rules_below })
+        .collect();
+
+    TableLayout { columns, cells, rows, invalid }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{resolve_spans, rules_below, CellLayout};
+    use super::CellLayout::*;
+
+    fn spans(grid: &[&[&str]]) -> (Vec<Vec<CellLayout>>, Vec<(usize, usize)>) {
+        let grid: Vec<Vec<String>> = grid.iter().map(|row| row.iter().map(|s| s.to_string()).collect()).collect();
+        resolve_spans(&grid, 1)
+    }
+
+    #[test]
+    fn test_colspan_rowspan() {
+        let (layout, invalid) = spans(&[
+            &["a", "<<", "b"],
+            &["c", "d", "e"],
+            &["^^", "f", "<<"],
+        ]);
+        assert!(invalid.is_empty());
+        assert_eq!(layout, vec![
+            vec![Cell { colspan: 2, rowspan: 1 }, Skip, Cell { colspan: 1, rowspan: 1 }],
+            vec![Cell { colspan: 1, rowspan: 2 }, Cell { colspan: 1, rowspan: 1 }, Cell { colspan: 1, rowspan: 1 }],
+            vec![Covered { colspan: 1 }, Cell { colspan: 2, rowspan: 1 }, Skip],
+        ]);
+    }
+
+    #[test]
+    fn test_block() {
+        let (layout, invalid) = spans(&[
+            &["h", "h", "h"],
+            &["a", "<<", "b"],
+            &["^^", "^^", "c"],
+        ]);
+        assert!(invalid.is_empty());
+        assert_eq!(layout[1], vec![Cell { colspan: 2, rowspan: 2 }, Skip, Cell { colspan: 1, rowspan: 1 }]);
+        assert_eq!(layout[2], vec![Covered { colspan: 2 }, Skip, Cell { colspan: 1, rowspan: 1 }]);
+        assert_eq!(rules_below(&layout), vec![
+            vec![true, true, true],
+            vec![false, false, true],
+            vec![true, true, true],
+        ]);
+    }
+
+    #[test]
+    fn test_invalid() {
+        // `^^` can't merge into the header and L-shapes aren't allowed
+        let (layout, invalid) = spans(&[
+            &["h", "h"],
+            &["^^", "a"],
+            &["b", "<<"],
+            &["^^", "c"],
+        ]);
+        assert_eq!(invalid, vec![(2, 0)]);
+        assert!(layout.iter().flatten().all(|&cell| cell == Cell { colspan: 1, rowspan: 1 }));
+    }
+}
@@ -0,0 +1,71 @@
+use std::borrow::Cow;
+use diagnostic::Spanned;
+
+use super::event::TableStyle;
+use crate::cskvp::Cskvp;
+use crate::error::DiagnosticCode;
+use crate::Diagnostics;
+
+const FONT_SIZES: &[&str] = &[
+    "tiny", "scriptsize", "footnotesize", "small", "normalsize", "large", "Large", "LARGE", "huge",
+    "Huge",
+];
+
+/// Takes the style options of a table from its element config.
+///
+/// * `booktabs`: only horizontal rules from the booktabs package
+/// * `zebra` / `zebra=<color>`: background color of every second row
+/// * `widths=1 2 1`: relative column widths instead of calculating them from the content
+/// * `fontsize=small`: LaTeX font size of the table
+/// * `longtable`: no floating table, repeating the head after page breaks
+pub fn take_style<'a>(cskvp: &mut Cskvp<'a>, diagnostics: &Diagnostics) -> TableStyle<'a> {
+    let zebra = match (cskvp.take_single("zebra"), cskvp.take_double("zebra")) {
+        (_, Some(Spanned { value: color, .. })) => Some(color),
+        (Some(_), None) => Some(Cow::Borrowed("gray!15")),
+        (None, None) => None,
+    };
+    let font_size = cskvp.take_double("fontsize").and_then(|Spanned { value: size, span }| {
+        if FONT_SIZES.contains(&size.as_ref()) {
+            return Some(size);
+        }
+        diagnostics
+            .warning(DiagnosticCode::InvalidTableStyle)
+            .with_error_label(span, format!("unknown font size `{}`", size))
+            .with_note(format!("supported font sizes are {}", FONT_SIZES.join(", ")))
+            .emit();
+        None
+    });
+    TableStyle {
+        booktabs: cskvp.take_single("booktabs").is_some(),
+        zebra,
+        font_size,
+        longtable: cskvp.take_single("longtable").is_some(),
+        widths: cskvp.take_double("widths"),
+    }
+}
+
+/// Parses explicit relative column widths like `1 2 1` into percentages.
+///
+/// Returns `None` if the widths are invalid or don't match the number of columns.
+pub fn widths(widths: Option<&Spanned<Cow<'_, str>>>, columns: usize, diagnostics: &Diagnostics) -> Option<Vec<f32>> {
+    let Spanned { value: widths, span } = widths?;
+    let parsed: Result<Vec<f32>, _> = widths.split_whitespace().map(str::parse::<f32>).collect();
+    let note = match parsed {
+        Ok(widths) if widths.len() == columns && widths.iter().all(|&w| w > 0.0) => {
+            let total: f32 = widths.iter().sum();
+            return Some(widths.into_iter().map(|w| w / total * 100.0).collect());
+        },
+        Ok(ref widths) if widths.len() != columns => {
+            format!("{} widths given, but the table has {} columns", widths.len(), columns)
+        },
+        Ok(_) => "widths must be positive".to_string(),
+        Err(e) => format!("widths must be numbers separated by spaces: {}", e),
+    };
+    diagnostics
+        .warning(DiagnosticCode::InvalidTableStyle)
+        .with_error_label(*span, "invalid column widths")
+        .with_note(note)
+        .with_note("calculating the widths from the content instead")
+        .emit();
+    None
+}
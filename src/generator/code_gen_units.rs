@@ -35,6 +35,8 @@ pub enum StackElement<'a, B: Backend<'a>> {
     Equation(B::Equation),
     NumberedEquation(B::NumberedEquation),
     Graphviz(B::Graphviz),
+    Gnuplot(B::Gnuplot),
+    Tikz(B::Tikz),
 
     // resolve context
     Context(Context, &'a Diagnostics),
@@ -60,9 +62,9 @@ impl<'a, B: Backend<'a>> StackElement<'a, B> {
             Tag::Figure(figure) => Ok(Figure(B::Figure::new(cfg, Spanned::new(figure, span), gen)?)),
             Tag::TableFigure(figure) => Ok(TableFigure(B::TableFigure::new(cfg, Spanned::new(figure, span), gen)?)),
             Tag::Table(table) => Ok(Table(B::Table::new(cfg, Spanned::new(table, span), gen)?)),
-            Tag::TableHead => Ok(TableHead(B::TableHead::new(cfg, Spanned::new((), span), gen)?)),
-            Tag::TableRow => Ok(TableRow(B::TableRow::new(cfg, Spanned::new((), span), gen)?)),
-            Tag::TableCell => Ok(TableCell(B::TableCell::new(cfg, Spanned::new((), span), gen)?)),
+            Tag::TableHead(head) => Ok(TableHead(B::TableHead::new(cfg, Spanned::new(head, span), gen)?)),
+            Tag::TableRow(row) => Ok(TableRow(B::TableRow::new(cfg, Spanned::new(row, span), gen)?)),
+            Tag::TableCell(cell) => Ok(TableCell(B::TableCell::new(cfg, Spanned::new(cell, span), gen)?)),
             Tag::InlineEmphasis => Ok(InlineEmphasis(B::InlineEmphasis::new(cfg, Spanned::new((), span), gen)?)),
             Tag::InlineStrong => Ok(InlineStrong(B::InlineStrong::new(cfg, Spanned::new((), span), gen)?)),
             Tag::InlineStrikethrough => Ok(InlineStrikethrough(B::InlineStrikethrough::new(cfg, Spanned::new((), span), gen)?)),
@@ -71,6 +73,8 @@ impl<'a, B: Backend<'a>> StackElement<'a, B> {
             Tag::Equation(equation) => Ok(Equation(B::Equation::new(cfg, Spanned::new(equation, span), gen)?)),
             Tag::NumberedEquation(equation) => Ok(NumberedEquation(B::NumberedEquation::new(cfg, Spanned::new(equation, span), gen)?)),
             Tag::Graphviz(graphviz) => Ok(Graphviz(B::Graphviz::new(cfg, Spanned::new(graphviz, span), gen)?)),
+            Tag::Gnuplot(gnuplot) => Ok(Gnuplot(B::Gnuplot::new(cfg, Spanned::new(gnuplot, span), gen)?)),
+            Tag::Tikz(tikz) => Ok(Tikz(B::Tikz::new(cfg, Spanned::new(tikz, span), gen)?)),
         }
     }
 
@@ -100,6 +104,8 @@ impl<'a, B: Backend<'a>> StackElement<'a, B> {
             Equation(s) => s.output_redirect(),
             NumberedEquation(s) => s.output_redirect(),
             Graphviz(s) => s.output_redirect(),
+            Gnuplot(s) => s.output_redirect(),
+            Tikz(s) => s.output_redirect(),
 
             Context(..) => None,
         }
@@ -120,9 +126,9 @@ impl<'a, B: Backend<'a>> StackElement<'a, B> {
             (Figure(s), Tag::Figure(_)) => s.finish(gen, peek),
             (TableFigure(s), Tag::TableFigure(_)) => s.finish(gen, peek),
             (Table(s), Tag::Table(_)) => s.finish(gen, peek),
-            (TableHead(s), Tag::TableHead) => s.finish(gen, peek),
-            (TableRow(s), Tag::TableRow) => s.finish(gen, peek),
-            (TableCell(s), Tag::TableCell) => s.finish(gen, peek),
+            (TableHead(s), Tag::TableHead(_)) => s.finish(gen, peek),
+            (TableRow(s), Tag::TableRow(_)) => s.finish(gen, peek),
+            (TableCell(s), Tag::TableCell(_)) => s.finish(gen, peek),
             (InlineEmphasis(s), Tag::InlineEmphasis) => s.finish(gen, peek),
             (InlineStrong(s), Tag::InlineStrong) => s.finish(gen, peek),
             (InlineStrikethrough(s), Tag::InlineStrikethrough) => s.finish(gen, peek),
@@ -131,12 +137,12 @@ impl<'a, B: Backend<'a>> StackElement<'a, B> {
             (Equation(s), Tag::Equation(_)) => s.finish(gen, peek),
             (NumberedEquation(s), Tag::NumberedEquation(_)) => s.finish(gen, peek),
             (Graphviz(s), Tag::Graphviz(_)) => s.finish(gen, peek),
+            (Gnuplot(s), Tag::Gnuplot(_)) => s.finish(gen, peek),
+            (Tikz(s), Tag::Tikz(_)) => s.finish(gen, peek),
             (state, tag) => unreachable!("invalid end tag {:?}, expected {:?}", tag, state),
         }
     }
 
-    // TODO: reomve allows
-    #[allow(dead_code)]
     pub fn is_graphviz(&self) -> bool {
         match self {
             Graphviz(_) => true,
@@ -144,9 +150,29 @@ impl<'a, B: Backend<'a>> StackElement<'a, B> {
         }
     }
 
+    pub fn is_gnuplot(&self) -> bool {
+        match self {
+            Gnuplot(_) => true,
+            _ => false
+        }
+    }
+
+    pub fn is_tikz(&self) -> bool {
+        match self {
+            Tikz(_) => true,
+            _ => false
+        }
+    }
+
+    /// Code which is passed verbatim to an external program
+    pub fn is_rendered_code(&self) -> bool {
+        self.is_graphviz() || self.is_gnuplot() || self.is_tikz()
+    }
+
+    // TODO: reomve allows
     #[allow(dead_code)]
     pub fn is_code_block(&self) -> bool {
-        self.is_graphviz() || match self {
+        self.is_rendered_code() || match self {
             CodeBlock(_) => true,
             _ => false
         }
@@ -183,7 +209,7 @@ impl<'a, B: Backend<'a>> StackElement<'a, B> {
 
     #[allow(dead_code)]
     pub fn is_code(&self) -> bool {
-        self.is_code_block() || self.is_inline_code() || self.is_graphviz()
+        self.is_code_block() || self.is_inline_code() || self.is_rendered_code()
     }
 
     pub fn is_math(&self) -> bool {
@@ -1,26 +1,41 @@
 use std::borrow::Cow;
+use std::ffi::OsString;
 use std::path::{PathBuf, Path};
-use std::fmt;
+use std::{fmt, fs, io};
 
 #[cfg(not(windows))]
 use librsvg::{Loader, LoadingError, RenderingError, CairoRenderer};
 #[cfg(not(windows))]
 use cairo::{PdfSurface, Context, Rectangle};
-use diagnostic::Spanned;
+use diagnostic::{Span, Spanned};
+
+use crate::cache::{self, CacheKey};
+use crate::error::DiagnosticCode;
+use crate::generator::{JobError, Jobs};
 
 pub use crate::frontend::{
     Tag,
     BiberReference,
     CodeBlock,
+    CodeStyle,
     Enumerate,
     Equation,
+    EquationEnvironment,
     Figure,
     FootnoteDefinition,
     FootnoteReference,
     Graphviz,
+    GraphvizFormat,
+    Gnuplot,
+    GnuplotTerminal,
+    Tikz,
     Header,
     InterLink,
     Table,
+    TableCell,
+    TableHead,
+    TableRow,
+    TableStyle,
     TaskListMarker,
     Url,
 };
@@ -38,6 +53,8 @@ pub enum Event<'a> {
     Text(Cow<'a, str>),
     Html(Cow<'a, str>),
     Latex(Cow<'a, str>),
+    /// Reviewer note, only emitted with `show_comments`
+    Comment(Cow<'a, str>),
     IncludeMarkdown(Box<Events<'a>>),
     FootnoteReference(FootnoteReference<'a>),
     BiberReferences(Vec<BiberReference<'a>>),
@@ -95,10 +112,12 @@ pub enum SvgConversionError {
     UnknownDimensions,
     LoadingError(LoadingError),
     RenderingError(RenderingError),
+    Io(io::Error),
 }
 #[cfg(windows)]
 pub enum SvgConversionError {
     SvgConversionNotSupportedOnWindows,
+    Io(io::Error),
 }
 
 impl fmt::Display for SvgConversionError {
@@ -108,15 +127,22 @@ impl fmt::Display for SvgConversionError {
             SvgConversionError::UnknownDimensions => write!(f, "unknown dimensions"),
             SvgConversionError::LoadingError(err) => write!(f, "can't load svg: {}", err),
             SvgConversionError::RenderingError(err) => write!(f, "conversion from svg to pdf failed: {}", err),
+            SvgConversionError::Io(err) => write!(f, "{}", err),
         }
         #[cfg(windows)]
         match self {
             SvgConversionError::SvgConversionNotSupportedOnWindows => {
                 write!(f, "svg conversion is not supported on windows, you'll need to manually convert the svg to e.g. a png")
             }
+            SvgConversionError::Io(err) => write!(f, "{}", err),
         }
     }
 }
+impl From<io::Error> for SvgConversionError {
+    fn from(err: io::Error) -> Self {
+        SvgConversionError::Io(err)
+    }
+}
 #[cfg(not(windows))]
 impl From<LoadingError> for SvgConversionError {
     fn from(err: LoadingError) -> Self {
@@ -131,20 +157,57 @@ impl From<RenderingError> for SvgConversionError {
 }
 
 impl<'a> Svg<'a> {
+    /// Schedules the conversion of the SVG to a PDF file, returning the path it will be written to.
+    ///
+    /// Conversions of the same svg in the same size are reused across builds.
+    pub fn schedule_pdf_conversion(
+        &self, cache_dir: &Path, jobs: &mut Jobs, span: Span,
+    ) -> io::Result<PathBuf> {
+        let key = CacheKey::new("svg2pdf")
+            .with(self.width.as_ref().map_or("", |width| &*width.value))
+            .with(self.height.as_ref().map_or("", |height| &*height.value))
+            .with(fs::read(&self.path)?);
+        let pdf_path = key.dir(cache_dir).join(self.pdf_file_name());
+        let owned = |size: &Option<Spanned<Cow<'a, str>>>| {
+            size.as_ref().map(|size| Spanned::new(Cow::Owned(size.value.to_string()), size.span))
+        };
+        let svg = Svg {
+            label: None,
+            caption: None,
+            title: None,
+            alt_text: None,
+            path: self.path.clone(),
+            scale: None,
+            width: owned(&self.width),
+            height: owned(&self.height),
+        };
+        let cache_dir = cache_dir.to_owned();
+        Ok(jobs.schedule(span, pdf_path, move || {
+            cache::cached(&cache_dir, key, |dir| svg.to_pdf_path(dir))
+                .map(drop)
+                .map_err(|e| {
+                    JobError::new(DiagnosticCode::SvgConversionError, "can't convert svg to pdf")
+                        .with_note(e.to_string())
+                })
+        }))
+    }
+
+    /// Name of the PDF file the SVG is converted to, e.g. `foo.svg.pdf` for `foo.svg`.
+    fn pdf_file_name(&self) -> OsString {
+        let mut name = self.path.file_name().unwrap().to_os_string();
+        name.push(".pdf");
+        name
+    }
+
     /// Converts the SVG to a PDF file and returns its path.
     ///
     /// This can be used by backends like latex, which don't support SVGs.
     #[cfg(not(windows))]
     pub fn to_pdf_path<P: AsRef<Path>>(&self, out_dir: P) -> Result<PathBuf, SvgConversionError> {
-        use std::ffi::OsString;
         use std::str::FromStr;
         use crate::frontend::Size;
 
-        let pdf_extension = self.path.extension()
-            .map(|s| { let mut s = s.to_os_string(); s.push(".pdf"); s })
-            .unwrap_or_else(|| OsString::from("pdf"));
-        let mut pdf_path = out_dir.as_ref().join(self.path.file_name().unwrap());
-        pdf_path.set_extension(pdf_extension);
+        let pdf_path = out_dir.as_ref().join(self.pdf_file_name());
         let handle = Loader::new().read_path(&self.path)?;
         let renderer = CairoRenderer::new(&handle);
 
@@ -191,6 +254,7 @@ impl<'a> From<FeEvent<'a>> for Event<'a> {
             FeEvent::Text(text) => Event::Text(text),
             FeEvent::Html(html) => Event::Html(html),
             FeEvent::Latex(latex) => Event::Latex(latex),
+            FeEvent::Comment(comment) => Event::Comment(comment),
             FeEvent::FootnoteReference(fnote) => Event::FootnoteReference(fnote),
             FeEvent::BiberReferences(biber) => Event::BiberReferences(biber),
             FeEvent::Url(url) => Event::Url(url),
@@ -199,6 +263,7 @@ impl<'a> From<FeEvent<'a>> for Event<'a> {
             FeEvent::ResolveInclude(_include) => {
                 unreachable!("ResolveInclude is handled by Generator")
             },
+            FeEvent::Chart(_chart) => unreachable!("Chart is handled by Generator"),
             FeEvent::Label(label) => Event::Label(label),
             FeEvent::SoftBreak => Event::SoftBreak,
             FeEvent::HardBreak => Event::HardBreak,
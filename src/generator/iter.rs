@@ -1,12 +1,13 @@
 use std::collections::VecDeque;
 use std::fs;
-use std::io::Write;
+use std::io::{self, Write};
 use std::iter::Fuse;
 use diagnostic::{Span, Spanned};
 
 use crate::backend::Backend;
+use crate::cache;
 use crate::error::{DiagnosticCode, Error, FatalResult, Result};
-use crate::frontend::{Event as FeEvent, EventKind as FeEventKind, Frontend, Include as FeInclude, Graphviz};
+use crate::frontend::{self, CodeBlock, CodeSelection, CodeStyle, CsvTable, TableStyle, Event as FeEvent, EventKind as FeEventKind, Frontend, Include as FeInclude, Graphviz, GraphvizEngine, GraphvizFormat, Chart};
 use crate::generator::event::{Event, Tag, Image, Pdf, Svg};
 use crate::generator::Generator;
 use crate::resolve::{Include, ResolveSecurity};
@@ -80,12 +81,14 @@ impl<'a> Iter<'a> {
             | FeEventKind::Text
             | FeEventKind::Html
             | FeEventKind::Latex
+            | FeEventKind::Comment
             | FeEventKind::FootnoteReference
             | FeEventKind::BiberReferences
             | FeEventKind::Url
             | FeEventKind::InterLink
             | FeEventKind::Include
             | FeEventKind::ResolveInclude
+            | FeEventKind::Chart
             | FeEventKind::Label
             | FeEventKind::SoftBreak
             | FeEventKind::HardBreak
@@ -122,15 +125,56 @@ impl<'a> Iter<'a> {
                 let include = gen.resolve(ResolveSecurity::Default, &include, span)?;
                 self.convert_include(Spanned::new(include, span), None, gen)
             },
+            FeEvent::Chart(chart) => self.convert_chart(Spanned::new(chart, span), gen),
             e => Ok(e.into()),
         }
     }
 
+    /// Renders a chart with the data of its resolved CSV / TSV file and includes the svg.
+    fn convert_chart(
+        &mut self, Spanned { value: chart, span }: Spanned<Chart<'a>>,
+        gen: &mut Generator<'a, impl Backend<'a>, impl Write>,
+    ) -> Result<Event<'a>> {
+        let Chart { spec, include, data_span } = chart;
+        let (data_path, delimiter) = match gen.resolve(include.resolve_security, &include.dst, data_span)? {
+            Include::Csv(path) => (path, ','),
+            Include::Tsv(path) => (path, '\t'),
+            _ => {
+                gen.diagnostics()
+                    .error(DiagnosticCode::InvalidChartData)
+                    .with_error_label(data_span, "the data of a chart must be a CSV or TSV file")
+                    .with_note("skipping over it")
+                    .emit();
+                return Err(Error::Diagnostic);
+            },
+        };
+        let content = fs::read_to_string(&data_path).map_err(|err| {
+            gen.diagnostics()
+                .error(DiagnosticCode::ErrorReadingTableFile)
+                .with_error_label(data_span, "can't read this data file")
+                .with_error_label(data_span, format!("cause: {}", err))
+                .with_note(format!("reading from path {}", data_path.display()))
+                .emit();
+            Error::Diagnostic
+        })?;
+        let svg = spec.to_svg(&content, delimiter).map_err(|message| {
+            gen.diagnostics()
+                .error(DiagnosticCode::InvalidChartData)
+                .with_error_label(data_span, message)
+                .with_note(format!("reading from path {}", data_path.display()))
+                .with_note("skipping over it")
+                .emit();
+            Error::Diagnostic
+        })?;
+        let path = cache::write_svg(&gen.cfg.cache_dir, "chart", &svg)?;
+        self.convert_include(Spanned::new(Include::Svg(path), span), Some(include), gen)
+    }
+
     fn convert_include(
         &mut self, Spanned { value: include, span }: Spanned<Include>, image: Option<FeInclude<'a>>,
         gen: &mut Generator<'a, impl Backend<'a>, impl Write>,
     ) -> Result<Event<'a>> {
-        let (label, caption, title, alt_text, scale, width, height) =
+        let (label, caption, title, alt_text, scale, width, height, header, align, table_style, code) =
             if let Some(FeInclude {
                 resolve_security: _,
                 label,
@@ -141,12 +185,23 @@ impl<'a> Iter<'a> {
                 scale,
                 width,
                 height,
+                header,
+                align,
+                table_style,
+                language,
+                lines,
+                region,
+                dedent,
+                code_style,
             }) = image
             {
-                (label, caption, title, alt_text, scale, width, height)
+                let code = (language, lines, region, dedent, code_style);
+                (label, caption, title, alt_text, scale, width, height, header, align, table_style, code)
             } else {
-                Default::default()
+                let code = (None, None, None, true, CodeStyle::default());
+                (None, None, None, None, None, None, None, true, None, TableStyle::default(), code)
             };
+        let delimiter = if let Include::Tsv(_) = include { '\t' } else { ',' };
         match include {
             Include::Command(command) => Ok(command.into()),
             Include::Markdown(path, context) => {
@@ -205,11 +260,90 @@ impl<'a> Iter<'a> {
                     scale,
                     width,
                     height,
+                    engine: GraphvizEngine::Dot,
+                    format: GraphvizFormat::Pdf,
+                    attributes: Vec::new(),
+                    code_span: None,
                 });
                 self.peek.push_back((Spanned::new(Event::Text(content.into()), span), self.last_kind));
                 self.peek.push_back((Spanned::new(Event::End(tag.clone()), span), self.last_kind));
                 Ok(Event::Start(tag))
-            }
+            },
+            Include::Csv(path) | Include::Tsv(path) => {
+                let content = fs::read_to_string(&path).map_err(|err| {
+                    gen.diagnostics()
+                        .error(DiagnosticCode::ErrorReadingTableFile)
+                        .with_error_label(span, "can't read this table file")
+                        .with_error_label(span, format!("cause: {}", err))
+                        .with_note(format!("reading from path {}", path.display()))
+                        .emit();
+                    Error::Diagnostic
+                })?;
+                let table = CsvTable { label, caption, header, align, style: table_style };
+                let mut events = frontend::csv_table_events(&content, delimiter, table, span, gen.diagnostics())
+                    .ok_or(Error::Diagnostic)?
+                    .into_iter()
+                    .map(|Spanned { value: event, span }| Spanned::new(Event::from(event), span));
+                let first = events.next().unwrap();
+                let kind = self.last_kind;
+                self.peek.extend(events.map(|event| (event, kind)));
+                Ok(first.value)
+            },
+            Include::Code(path, extension, fragment) => {
+                let (language, lines, region, dedent, mut style) = code;
+                let content = fs::read_to_string(&path).map_err(|err| {
+                    if err.kind() == io::ErrorKind::InvalidData {
+                        // files of unknown formats are included as code, but binary files can't be
+                        gen.diagnostics()
+                            .error(DiagnosticCode::UnknownFileFormat)
+                            .with_error_label(span, format!("unknown file format {:?}", extension))
+                            .with_note(format!("reading from path {}", path.display()))
+                            .emit();
+                        return Error::Diagnostic;
+                    }
+                    gen.diagnostics()
+                        .error(DiagnosticCode::ErrorReadingCodeFile)
+                        .with_error_label(span, "can't read this source code file")
+                        .with_error_label(span, format!("cause: {}", err))
+                        .with_note(format!("reading from path {}", path.display()))
+                        .emit();
+                    Error::Diagnostic
+                })?;
+                // the element config takes precedence over the url fragment
+                let selection = match (&lines, &region, &fragment) {
+                    (Some(Spanned { value: lines, .. }), _, _) => CodeSelection::from_lines(lines),
+                    (None, Some(Spanned { value: region, .. }), _) => Ok(CodeSelection::Region(region)),
+                    (None, None, Some(fragment)) => CodeSelection::from_fragment(fragment),
+                    (None, None, None) => Ok(CodeSelection::All),
+                };
+                if let Ok(CodeSelection::Lines { start, .. }) = selection {
+                    // number the lines like in the file
+                    style.firstline = style.firstline.or(Some(start));
+                }
+                let code = selection.and_then(|selection| frontend::select_code(&content, &selection, dedent));
+                let code = code.map_err(|err| {
+                    let span = lines.as_ref().or(region.as_ref()).map_or(span, |s| s.span);
+                    gen.diagnostics()
+                        .error(DiagnosticCode::InvalidCodeSelection)
+                        .with_error_label(span, "can't select the source code to include")
+                        .with_note(err)
+                        .with_note(format!("in file {}", path.display()))
+                        .emit();
+                    Error::Diagnostic
+                })?;
+                let tag = Tag::CodeBlock(CodeBlock {
+                    label,
+                    caption,
+                    language: Some(language.unwrap_or_else(|| {
+                        Spanned::new(gen.cfg.code.language(&extension).to_string().into(), span)
+                    })),
+                    basicstyle: None,
+                    style,
+                });
+                self.peek.push_back((Spanned::new(Event::Text(code.into()), span), self.last_kind));
+                self.peek.push_back((Spanned::new(Event::End(tag.clone()), span), self.last_kind));
+                Ok(Event::Start(tag))
+            },
         }
     }
 }
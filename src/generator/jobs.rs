@@ -0,0 +1,160 @@
+//! Renders scheduled during code generation, like graphviz graphs and svg to pdf conversions.
+//!
+//! Code generation only schedules a job and writes the path its artifact will be written to. All
+//! jobs of a document are run in parallel once it's generated, before LaTeX is run on it.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::io;
+use std::mem;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::thread;
+use diagnostic::Span;
+
+use crate::error::{DiagnosticCode, Diagnostics};
+
+type Run = Box<dyn FnOnce() -> Result<(), JobError> + Send>;
+
+#[derive(Default)]
+pub struct Jobs {
+    /// Jobs with the span of the element they render
+    jobs: Vec<(Span, Run)>,
+    /// Artifacts of the scheduled jobs, such that equal renders only run once
+    artifacts: HashSet<PathBuf>,
+}
+
+impl fmt::Debug for Jobs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Jobs")
+            .field("jobs", &self.jobs.len())
+            .field("artifacts", &self.artifacts)
+            .finish()
+    }
+}
+
+impl Jobs {
+    /// Schedules `run` to write the artifact at the given path, returning that path.
+    ///
+    /// If a job for the same artifact is already scheduled, `run` is dropped.
+    pub fn schedule(
+        &mut self, span: Span, artifact: PathBuf,
+        run: impl FnOnce() -> Result<(), JobError> + Send + 'static,
+    ) -> PathBuf {
+        if self.artifacts.insert(artifact.clone()) {
+            self.jobs.push((span, Box::new(run)));
+        }
+        artifact
+    }
+
+    /// Runs all scheduled jobs on up to `threads` threads and emits the diagnostics of failed ones
+    /// in the order they were scheduled in.
+    ///
+    /// Failed jobs don't write their artifact, such that the document shows a placeholder instead.
+    /// Returns if all jobs succeeded.
+    pub fn run(&mut self, threads: usize, diagnostics: &Diagnostics) -> bool {
+        let jobs = mem::take(&mut self.jobs);
+        self.artifacts.clear();
+        let threads = threads.max(1).min(jobs.len());
+        let queue = Mutex::new(jobs.into_iter().enumerate());
+        let errors = Mutex::new(Vec::new());
+        thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| loop {
+                    // the lock must be released before running the job
+                    let job = queue.lock().unwrap().next();
+                    let (i, (span, run)) = match job {
+                        Some(job) => job,
+                        None => break,
+                    };
+                    if let Err(e) = run() {
+                        errors.lock().unwrap().push((i, span, e));
+                    }
+                });
+            }
+        });
+
+        let mut errors = errors.into_inner().unwrap();
+        errors.sort_by_key(|&(i, ..)| i);
+        let success = errors.is_empty();
+        for (_, span, error) in errors {
+            error.with_note("showing a placeholder instead").emit(span, diagnostics);
+        }
+        success
+    }
+}
+
+/// Diagnostic of a failed job, emitted once all jobs are finished.
+pub struct JobError {
+    code: DiagnosticCode,
+    /// Label at the span of the job's element
+    label: String,
+    labels: Vec<(Span, String)>,
+    notes: Vec<String>,
+}
+
+impl JobError {
+    pub fn new(code: DiagnosticCode, label: impl Into<String>) -> JobError {
+        JobError { code, label: label.into(), labels: Vec::new(), notes: Vec::new() }
+    }
+
+    pub fn with_error_label(mut self, span: Span, label: impl Into<String>) -> JobError {
+        self.labels.push((span, label.into()));
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> JobError {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Emits the diagnostic with the main label at the given span of the job's element.
+    pub fn emit(self, span: Span, diagnostics: &Diagnostics) {
+        let mut diagnostic = diagnostics.error(self.code).with_error_label(span, self.label);
+        for (span, label) in self.labels {
+            diagnostic = diagnostic.with_error_label(span, label);
+        }
+        for note in self.notes {
+            diagnostic = diagnostic.with_note(note);
+        }
+        diagnostic.emit();
+    }
+}
+
+impl From<io::Error> for JobError {
+    fn from(err: io::Error) -> Self {
+        JobError::new(DiagnosticCode::ErrorWritingToCache, "can't cache the render of this")
+            .with_note(format!("cause: {}", err))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use diagnostic::{FileId, Span};
+
+    use super::Jobs;
+    use crate::error::Diagnostics;
+
+    #[test]
+    fn test_run() {
+        let span = Span::new(FileId::synthetic("test"), 0, 0);
+        let runs = Arc::new(AtomicUsize::new(0));
+        let mut jobs = Jobs::default();
+        for artifact in &["a.pdf", "b.pdf", "a.pdf", "c.pdf"] {
+            let runs = Arc::clone(&runs);
+            let path = jobs.schedule(span, PathBuf::from(artifact), move || {
+                runs.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            });
+            assert_eq!(path, PathBuf::from(artifact));
+        }
+        assert!(jobs.run(2, &Diagnostics::new()));
+        // the second `a.pdf` is the same render
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        assert!(jobs.run(2, &Diagnostics::new()));
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+    }
+}
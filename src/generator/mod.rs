@@ -12,12 +12,14 @@ use crate::error::Diagnostics;
 mod code_gen_units;
 pub mod event;
 mod iter;
+pub mod jobs;
 mod stack;
 
+pub use self::jobs::{JobError, Jobs};
 pub use self::stack::Stack;
 
 use self::code_gen_units::StackElement;
-use self::event::Event;
+use self::event::{Event, Tag};
 use crate::error::{Error, FatalResult, Result};
 use crate::generator::iter::Iter;
 
@@ -29,6 +31,10 @@ pub struct Generator<'a, B: Backend<'a>, W: Write> {
     resolver: Resolver,
     template: Option<String>,
     diagnostics: &'a Diagnostics,
+    jobs: Jobs,
+    /// Last character of the previous text within the current run of inline text, such that smart
+    /// quotes right after inline formatting like `*foo*"bar"` get the right direction.
+    prev_char: Option<char>,
 }
 
 pub struct Events<'a> {
@@ -59,6 +65,8 @@ impl<'a, B: Backend<'a>, W: Write> Generator<'a, B, W> {
             resolver: Resolver::new(cfg.project_root.clone(), cfg.document_folder.clone(), cfg.temp_dir.clone()),
             template,
             diagnostics,
+            jobs: Jobs::default(),
+            prev_char: None,
         }
     }
 
@@ -84,9 +92,17 @@ impl<'a, B: Backend<'a>, W: Write> Generator<'a, B, W> {
             assert!(self.stack.pop().is_none());
             self.backend.gen_epilogue(self.cfg, &mut self.default_out, &*self.diagnostics)?;
         }
+        self.run_jobs();
         Ok(())
     }
 
+    /// Runs the renders scheduled while generating, which the generated document refers to.
+    ///
+    /// The document shows a placeholder instead of the artifacts of failed renders.
+    pub fn run_jobs(&mut self) {
+        self.jobs.run(self.cfg.jobs, self.diagnostics);
+    }
+
     pub fn generate_body(&mut self, events: Events<'a>) -> FatalResult<()> {
         self.stack.push(StackElement::Context(events.context, self.diagnostics));
         let mut events = events.events;
@@ -113,6 +129,16 @@ impl<'a, B: Backend<'a>, W: Write> Generator<'a, B, W> {
         &mut self, event: Spanned<Event<'a>>, config: &'a Config, peek: Option<Spanned<&Event<'a>>>,
     ) -> Result<()> {
         let Spanned { value: event, span } = event;
+        let continues_text = match &event {
+            Event::Text(_) => true,
+            Event::Start(tag) | Event::End(tag) => matches!(tag,
+                Tag::InlineEmphasis | Tag::InlineStrong | Tag::InlineStrikethrough | Tag::InlineCode
+                | Tag::Url(_) | Tag::InterLink(_)),
+            _ => false,
+        };
+        if !continues_text {
+            self.prev_char = None;
+        }
         if let Event::End(tag) = event {
             let state = self.stack.pop().unwrap();
             state.finish(tag, self, peek)?;
@@ -128,6 +154,9 @@ impl<'a, B: Backend<'a>, W: Write> Generator<'a, B, W> {
             Event::Text(text) => B::Text::new(config, Spanned::new(text, span), self)?.finish(self, peek)?,
             Event::Html(html) => B::Text::new(config, Spanned::new(html, span), self)?.finish(self, peek)?,
             Event::Latex(latex) => B::Latex::new(config, Spanned::new(latex, span), self)?.finish(self, peek)?,
+            Event::Comment(comment) => {
+                B::Comment::new(config, Spanned::new(comment, span), self)?.finish(self, peek)?
+            },
             Event::IncludeMarkdown(events) => self.generate_body(*events)?,
             Event::FootnoteReference(fnote) => {
                 B::FootnoteReference::new(config, Spanned::new(fnote, span), self)?.finish(self, peek)?
@@ -160,7 +189,11 @@ impl<'a, B: Backend<'a>, W: Write> Generator<'a, B, W> {
     }
 
     pub fn stack(&mut self) -> Stack<'a, '_, B, W> {
-        Stack::new(&mut self.default_out, &mut self.stack)
+        Stack::new(&mut self.default_out, &mut self.stack, &mut self.jobs, &mut self.prev_char)
+    }
+
+    pub fn jobs(&mut self) -> &mut Jobs {
+        &mut self.jobs
     }
 
     pub fn iter_stack(&self) -> impl Iterator<Item = &StackElement<'a, B>> {
@@ -179,13 +212,14 @@ impl<'a, B: Backend<'a>, W: Write> Generator<'a, B, W> {
         let mut context = None;
         let mut out = None;
         for state in self.stack.iter_mut().rev() {
-            if context.is_none() {
-                match state {
-                    StackElement::Context(ctx, diagnostics) => context = Some((ctx, diagnostics)),
-                    _ => (),
-                }
-            } else if out.is_none() {
-                out = state.output_redirect();
+            match state {
+                StackElement::Context(ctx, diagnostics) => if context.is_none() {
+                    context = Some((ctx, diagnostics));
+                },
+                // redirects of elements above the context apply as well
+                state => if out.is_none() {
+                    out = state.output_redirect();
+                },
             }
         }
 
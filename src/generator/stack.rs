@@ -1,17 +1,22 @@
 use std::io::Write;
 
-use super::StackElement;
+use super::{Jobs, StackElement};
 use crate::backend::Backend;
 use crate::Diagnostics;
 
 pub struct Stack<'a, 'b, B: Backend<'a>, W: Write> {
     default_out: &'b mut W,
     stack: &'b mut [StackElement<'a, B>],
+    jobs: &'b mut Jobs,
+    prev_char: &'b mut Option<char>,
 }
 
 impl<'a: 'b, 'b, B: Backend<'a> + 'b, W: Write> Stack<'a, 'b, B, W> {
-    pub(super) fn new(default_out: &'b mut W, stack: &'b mut [StackElement<'a, B>]) -> Self {
-        Stack { default_out, stack }
+    pub(super) fn new(
+        default_out: &'b mut W, stack: &'b mut [StackElement<'a, B>], jobs: &'b mut Jobs,
+        prev_char: &'b mut Option<char>,
+    ) -> Self {
+        Stack { default_out, stack, jobs, prev_char }
     }
 
     pub fn iter(&self) -> impl Iterator<Item = &StackElement<'a, B>> {
@@ -38,4 +43,13 @@ impl<'a: 'b, 'b, B: Backend<'a> + 'b, W: Write> Stack<'a, 'b, B, W> {
             .next()
             .unwrap()
     }
+
+    pub fn jobs(&mut self) -> &mut Jobs {
+        self.jobs
+    }
+
+    /// Last character of the previous text within the current run of inline text.
+    pub fn prev_char(&mut self) -> &mut Option<char> {
+        self.prev_char
+    }
 }
@@ -40,6 +40,7 @@ use structopt::StructOpt;
 use tempdir::TempDir;
 
 mod backend;
+mod cache;
 mod config;
 mod cskvp;
 mod error;
@@ -112,7 +113,7 @@ fn main() {
         None => FileConfig::default(),
     };
     let tmpdir = TempDir::new("heradoc").expect("can't create tempdir");
-    let cfg = Config::new(args, infile, file, cfgfile_folder, &tmpdir);
+    let cfg = Config::new(args, infile, file, cfgfile_folder, &tmpdir, &diagnostics);
     if cfg.out_dir != cfg.temp_dir {
         // While initializing the config, some files may already be downloaded.
         // Thus we must only clear the output directory if it's not a temporary directory.
@@ -84,6 +84,11 @@ pub enum Include {
     Svg(PathBuf),
     Pdf(PathBuf),
     Graphviz(PathBuf),
+    Csv(PathBuf),
+    Tsv(PathBuf),
+    /// Source code file with its extension, which is resolved like the language of code blocks,
+    /// and the url fragment selecting a part of the file, e.g. `L10-L42`
+    Code(PathBuf, String, Option<String>),
 }
 
 /// A direct command to the generator.
@@ -356,12 +356,12 @@ fn to_include(
         Some("svg") => Ok(Include::Svg(path)),
         Some("pdf") => Ok(Include::Pdf(path)),
         Some("gv") | Some("dot") => Ok(Include::Graphviz(path)),
+        Some("csv") => Ok(Include::Csv(path)),
+        Some("tsv") | Some("tab") => Ok(Include::Tsv(path)),
+        // anything else is included as source code
         Some(ext) => {
-            diagnostics
-                .error(DiagnosticCode::UnknownFileFormat)
-                .with_error_label(span, format!("unknown file format {:?}", ext))
-                .emit();
-            Err(Error::Diagnostic)
+            let fragment = context.url().fragment().map(str::to_string);
+            Ok(Include::Code(path, ext.to_string(), fragment))
         },
         None => {
             diagnostics
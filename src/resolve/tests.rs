@@ -36,7 +36,9 @@ macro_rules! assert_match {
 /// ├── images
 /// │   └── image.png
 /// ├── image.png
+/// ├── data.csv
 /// ├── main.md
+/// ├── main.rs
 /// ├── pdf.pdf
 /// └── test.md
 /// ````
@@ -46,6 +48,8 @@ fn prepare() -> (TempDir, Span, Resolver, Diagnostics) {
     let _ = File::create(tmpdir.path().join("test.md")).expect("Can't create test.md");
     let _ = File::create(tmpdir.path().join("image.png")).expect("Can't create image.png");
     let _ = File::create(tmpdir.path().join("pdf.pdf")).expect("Can't create pdf.pdf");
+    let _ = File::create(tmpdir.path().join("data.csv")).expect("Can't create data.csv");
+    let _ = File::create(tmpdir.path().join("main.rs")).expect("Can't create main.rs");
     fs::create_dir(tmpdir.path().join("chapters")).expect("Can't create chapter subdir");
     fs::create_dir(tmpdir.path().join("images")).expect("Can't create images subdir");
     let _ = File::create(tmpdir.path().join("chapters/chapter1.md")).expect("Can't create chapters/chapter1.md");
@@ -87,6 +91,16 @@ fn relative_to_project_root() {
             .expect("failed to resolve `/pdf.pdf`");
         assert_match!(pdf, Include::Pdf(path) if path == &project_root.path().join("pdf.pdf"));
 
+        let csv = resolver
+            .resolve(ResolveSecurity::Default, &ctx, "/data.csv", span, &diagnostics)
+            .expect("failed to resolve `/data.csv`");
+        assert_match!(csv, Include::Csv(path) if path == &project_root.path().join("data.csv"));
+
+        let code = resolver
+            .resolve(ResolveSecurity::Default, &ctx, "/main.rs#L10-L42", span, &diagnostics)
+            .expect("failed to resolve `/main.rs#L10-L42`");
+        assert_match!(code, Include::Code(path, ext, Some(fragment)) if ext == "rs" && path == &project_root.path().join("main.rs") && fragment == "L10-L42");
+
         let chapter1 = resolver
             .resolve(ResolveSecurity::Default, &ctx, "/chapters/chapter1.md", span, &diagnostics)
             .expect("failed to resolve `/chapters/chapter1.md`");